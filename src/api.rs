@@ -1,14 +1,14 @@
-use std::{
-    collections::HashMap,
-    io::{BufReader, Read},
-};
+use std::{collections::HashMap, time::Duration};
 
-use reqwest::{blocking, header::HeaderMap};
+use reqwest::{
+    StatusCode, blocking,
+    header::{ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER},
+};
 use serde::{Deserialize, Serialize};
 
-use crate::{item_search::data_types, log_match_panic, log_panic};
+use crate::{item_search::data_types, log_panic};
 
-use tracing::{instrument, trace, warn};
+use tracing::{debug, instrument, trace, warn};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MappingItem {
@@ -34,13 +34,12 @@ pub struct MappingItem {
 // [Oldest](Timespan::Oldest) will provide an average of the prices for:
 //  `5` minutes
 //  `1` hour
+//  `24` hours
+// A *specific item* timeseries is requested separately.
+// See [`Api::request_timeseries`]
 pub enum Timespan {
     Latest,
-    Oldest(u16), // 5(minutes), 1(hour)
-                 // TODO: Implement *SPECIFIC ITEM* timeseries? Not really needed...
-                 // Maybe only if the item can't be found in the current timespan?
-                 // -> Increase the lookback time
-                 //Series(u16), // 5(minutes), 1(hours), 6(hours), 24(hours)
+    Oldest(u16), // 5(minutes), 1(hour), 24(hours)
 }
 
 #[derive(Debug)]
@@ -48,6 +47,120 @@ pub struct Api {
     url: String,
     timespan: Timespan,
     headers: ApiHeaders,
+    retry: RetryPolicy,
+}
+
+/// Failure of a single API request
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Invalid request headers: {0}")]
+    Headers(String),
+    /// Connection, timeout or body read failures
+    #[error("Request to {url} failed: {source}")]
+    Transport {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{url} responded with {status}")]
+    HttpStatus { url: String, status: StatusCode },
+    /// Too many requests (429). `retry_after` from the response when provided
+    #[error("Rate limited by {url}")]
+    RateLimit {
+        url: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("Failed to decode response from {url}: {source}")]
+    Decode {
+        url: String,
+        #[source]
+        source: serde_yaml_ng::Error,
+    },
+}
+
+impl ApiError {
+    /// Whether sending the same request again may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport { .. } | Self::RateLimit { .. } => true,
+            Self::HttpStatus { status, .. } => status.is_server_error(),
+            Self::Headers(_) | Self::Decode { .. } => false,
+        }
+    }
+}
+
+/// Cache validators returned with a response.
+/// Sent back with the next request, so unchanged data is not downloaded again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Add `If-None-Match` and `If-Modified-Since` headers
+    fn add_to(&self, header_map: &mut HeaderMap) -> Result<(), ApiError> {
+        let headers = [(IF_NONE_MATCH, &self.etag), (IF_MODIFIED_SINCE, &self.last_modified)];
+        for (name, value) in headers {
+            if let Some(value) = value {
+                let value = HeaderValue::from_str(value).map_err(|e| ApiError::Headers(e.to_string()))?;
+                header_map.insert(name, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// When the stored prices were fetched, with the validators of that response.
+/// Stored next to the price data. See `filepaths.price_metadata`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FetchMetadata {
+    pub fetched_at: i64, // Unix Timestamp of the last download
+    pub checked_at: i64, // Unix Timestamp of the last request, including unmodified responses
+    #[serde(flatten)]
+    pub validators: Validators,
+}
+
+/// See [`Api::request_item_prices_if_modified`]
+#[derive(Debug)]
+pub enum PriceResponse {
+    Modified(data_types::latest::PriceDataType, Validators),
+    NotModified,
+}
+
+/// A successful response. `body` is `None` when not modified
+struct Fetched {
+    body: Option<Vec<u8>>,
+    validators: Validators,
+}
+
+/// How failed requests are retried. See [`crate::config::Api`]
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl RetryPolicy {
+    /// `backoff * 2^attempt`, or longer when the server asked us to wait
+    pub fn delay(&self, attempt: u32, error: &ApiError) -> Duration {
+        let backoff = self.backoff.saturating_mul(2u32.saturating_pow(attempt));
+        match error {
+            ApiError::RateLimit { retry_after: Some(wait), .. } => backoff.max(*wait),
+            _ => backoff,
+        }
+    }
+}
+
+impl From<&crate::config::Api> for RetryPolicy {
+    fn from(api_config: &crate::config::Api) -> Self {
+        Self {
+            max_retries: api_config.max_retries,
+            backoff: Duration::from_millis(api_config.backoff_ms),
+            timeout: Duration::from_secs(api_config.timeout_secs),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -61,6 +174,7 @@ impl From<crate::config::TimeSpan> for Timespan {
             crate::config::TimeSpan::Latest => Self::Latest,
             crate::config::TimeSpan::FiveMinute => Self::Oldest(5),
             crate::config::TimeSpan::OneHour => Self::Oldest(1),
+            crate::config::TimeSpan::TwentyFourHour => Self::Oldest(24),
         }
     }
 }
@@ -73,6 +187,7 @@ impl Timespan {
             Self::Oldest(t) => match t {
                 5 => "/5m",
                 1 => "/1h",
+                24 => "/24h",
                 unknown => log_panic("Unimplemented timespan", unknown),
             },
         }
@@ -107,8 +222,9 @@ impl Api {
         //Api { config: config, headers: config.auth_headers.clone().into()}
         Api {
             url: api_config.url.clone(),
-            timespan: Timespan::from(api_config.timespan.clone()),
+            timespan: Timespan::from(api_config.timespan),
             headers: ApiHeaders::from(api_config.auth_headers.clone()),
+            retry: RetryPolicy::from(api_config),
         }
     }
 
@@ -143,48 +259,215 @@ impl Api {
         trace!(new = ?self.headers);
     }
 
+    fn header_map(&self) -> Result<HeaderMap, ApiError> {
+        // TODO: Optimise by storing headers as HeaderMap in API struct?
+        HeaderMap::try_from(&self.headers.headers).map_err(|e| ApiError::Headers(e.to_string()))
+    }
+
+    /// Send a single GET request and read the full response body.
+    /// The body is `None` when not modified since the validators in `header_map`.
+    fn get_once(
+        client: &blocking::Client,
+        target: &str,
+        query: &[(&str, String)],
+        header_map: HeaderMap,
+    ) -> Result<Fetched, ApiError> {
+        let transport = |source| ApiError::Transport { url: target.to_string(), source };
+
+        let mut res_build = client.get(target).headers(header_map);
+        if !query.is_empty() {
+            res_build = res_build.query(query);
+        }
+
+        let res = res_build.send().map_err(transport)?;
+        let status = res.status();
+        trace!(desc = "Recieved response", status = %status);
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = res.headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            return Err(ApiError::RateLimit { url: target.to_string(), retry_after });
+        }
+
+        let header_string = |name| res.headers()
+            .get(name)
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+            .map(str::to_string);
+        let validators = Validators {
+            etag: header_string(ETAG),
+            last_modified: header_string(LAST_MODIFIED),
+        };
+
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched { body: None, validators });
+        }
+        if !status.is_success() {
+            return Err(ApiError::HttpStatus { url: target.to_string(), status });
+        }
+
+        let body = res.bytes().map(|body| body.to_vec()).map_err(transport)?;
+        Ok(Fetched { body: Some(body), validators })
+    }
+
+    /// GET `target`, retrying [retryable](ApiError::is_retryable) failures
+    /// with exponential backoff. See [`RetryPolicy`]
+    /// Sends `previous` validators so unchanged data is not downloaded again.
+    /// # Errors
+    /// The last error once the retries are used up, or the first non-retryable error.
+    fn get_with_retries(&self, target: &str, query: &[(&str, String)], previous: &Validators)
+        -> Result<Fetched, ApiError> {
+        let mut header_map: HeaderMap = self.header_map()?;
+        previous.add_to(&mut header_map)?;
+
+        let client = blocking::Client::builder()
+            .timeout(self.retry.timeout)
+            .build()
+            .map_err(|source| ApiError::Transport { url: target.to_string(), source })?;
+
+        let mut attempt: u32 = 0;
+        loop {
+            match Self::get_once(&client, target, query, header_map.clone()) {
+                Ok(fetched) => return Ok(fetched),
+                Err(e) if e.is_retryable() && attempt < self.retry.max_retries => {
+                    let delay = self.retry.delay(attempt, &e);
+                    warn!(desc = "Request failed. Retrying.", error = %e, attempt, delay_ms = delay.as_millis());
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    warn!(desc = "Request failed.", error = %e, attempt);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
     /// Make a request to the [config url](Api::config::api::url)
     /// At the current endpoint
-    #[tracing::instrument(name = "api::request")]
-    pub fn request_item_prices(&self) -> data_types::latest::PriceDataType {
-        // TODO: Optimise by storing headers as HeaderMap in API struct?
-        let header_map: HeaderMap = log_match_panic(
-            HeaderMap::try_from(&self.headers.headers),
-            "Made HeaderMap",
-            "HeaderMap conversion error",
-        );
+    /// See [`Api::request_item_prices_if_modified`]
+    /// # Errors
+    /// See [`ApiError`]
+    pub fn request_item_prices(&self) -> Result<data_types::latest::PriceDataType, ApiError> {
+        match self.request_item_prices_if_modified(&Validators::default())? {
+            PriceResponse::Modified(prices, _) => Ok(prices),
+            // Only expected when validators are sent
+            PriceResponse::NotModified => Err(ApiError::HttpStatus {
+                url: self.url.clone() + &self.timespan.get_endpoint(),
+                status: StatusCode::NOT_MODIFIED,
+            }),
+        }
+    }
 
-        let endpoint: String = self.timespan.get_endpoint();
-        let target: String = self.url.clone() + &endpoint;
+    /// Request prices at the current endpoint,
+    /// unless they are unchanged since the `previous` response.
+    /// Averaged ([Oldest](Timespan::Oldest)) responses are converted to the `latest` format,
+    /// keeping their trade volumes
+    /// # Errors
+    /// See [`ApiError`]
+    pub fn request_item_prices_if_modified(&self, previous: &Validators) -> Result<PriceResponse, ApiError> {
+        self.request_prices_at(self.timespan, previous)
+    }
 
-        let client = blocking::Client::new();
-        let res_build = client.get(target).headers(header_map);
+    #[tracing::instrument(name = "api::request", skip(self))]
+    fn request_prices_at(&self, timespan: Timespan, previous: &Validators) -> Result<PriceResponse, ApiError> {
+        let endpoint: String = timespan.get_endpoint();
+        let target: String = self.url.clone() + &endpoint;
 
-        let mut res = log_match_panic(res_build.send(), "Recieved response", "Request sent error");
+        let Fetched { body, validators } = self.get_with_retries(&target, &[], previous)?;
+        let Some(body) = body else {
+            debug!(desc = "Prices not modified", validators = ?previous);
+            return Ok(PriceResponse::NotModified);
+        };
+        let decode = |source| ApiError::Decode { url: target.clone(), source };
 
         // Decode response
-        let buffer = BufReader::new(res.by_ref());
+        let prices = match timespan {
+            Timespan::Latest => serde_yaml_ng::from_slice(&body).map_err(decode)?,
+            Timespan::Oldest(_) => {
+                let averaged: data_types::oldest::PriceDataType =
+                    serde_yaml_ng::from_slice(&body).map_err(decode)?;
+                trace!(desc = "Converting averaged prices", timestamp = averaged.timestamp);
 
-        log_match_panic(
-            serde_yaml_ng::from_reader(buffer),
-            "Deserializing API response",
-            "Failed to deserialize API response",
-        )
+                averaged.into()
+            }
+        };
+
+        Ok(PriceResponse::Modified(prices, validators))
     }
 
-    /// Wrapper around [`self.request_item_prices`]
+    /// Request the last (up to 365) averaged prices of a single item
+    /// Spaced by `timestep`
+    /// # Errors
+    /// See [`ApiError`]
+    #[tracing::instrument(name = "api::timeseries", skip(self))]
+    pub fn request_timeseries(
+        &self,
+        item_id: &str,
+        timestep: crate::config::TimeStep,
+    ) -> Result<data_types::timeseries::TimeseriesDataType, ApiError> {
+        let target: String = self.url.clone() + "/timeseries";
+        let query = [("id", item_id.to_string()), ("timestep", timestep.to_string())];
+
+        let Fetched { body, .. } = self.get_with_retries(&target, &query, &Validators::default())?;
+        let body = body.ok_or_else(|| ApiError::HttpStatus {
+            url: target.clone(),
+            status: StatusCode::NOT_MODIFIED,
+        })?;
+
+        // Decode response
+        serde_yaml_ng::from_slice(&body).map_err(|source| ApiError::Decode { url: target, source })
+    }
+
+    /// [`Api::request_item_prices`] at another `timespan`, e.g. for its trade volumes
+    /// # Errors
+    /// See [`ApiError`]
     pub fn request_timespan_prices(
-        &mut self,
+        &self,
         timespan: Timespan,
-    ) -> data_types::latest::PriceDataType {
-        let old_timespan = self.timespan;
+    ) -> Result<data_types::latest::PriceDataType, ApiError> {
+        match self.request_prices_at(timespan, &Validators::default())? {
+            PriceResponse::Modified(prices, _) => Ok(prices),
+            PriceResponse::NotModified => Err(ApiError::HttpStatus {
+                url: self.url.clone() + &timespan.get_endpoint(),
+                status: StatusCode::NOT_MODIFIED,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        self.timespan = timespan;
-        let res = self.request_item_prices();
+    #[test]
+    /// Backoff doubles per attempt, but waits at least as long as a rate limit asks
+    fn retry_delay() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(30),
+        };
+        let status = ApiError::HttpStatus {
+            url: String::new(),
+            status: StatusCode::BAD_GATEWAY,
+        };
+        assert!(status.is_retryable());
+        assert_eq!(policy.delay(0, &status), Duration::from_millis(500));
+        assert_eq!(policy.delay(2, &status), Duration::from_secs(2));
 
-        // Restore actual timespan
-        self.timespan = old_timespan;
+        let limited = ApiError::RateLimit {
+            url: String::new(),
+            retry_after: Some(Duration::from_secs(10)),
+        };
+        assert_eq!(policy.delay(1, &limited), Duration::from_secs(10));
 
-        res
+        let not_found = ApiError::HttpStatus {
+            url: String::new(),
+            status: StatusCode::NOT_FOUND,
+        };
+        assert!(!not_found.is_retryable());
     }
 }
@@ -16,7 +16,7 @@ pub struct Config {
     pub levels: Levels,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TimeSpan {
     #[default]
     #[serde(rename = "latest")]
@@ -25,7 +25,57 @@ pub enum TimeSpan {
     FiveMinute,
     #[serde(rename = "1h")]
     OneHour,
-    // TODO: Extend to 6h(our), 24h(our)? This is only for specific item lookup
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+    // TODO: Extend to 6h(our)? This is only for specific item lookup
+}
+
+impl TimeSpan {
+    /// Length of the averaged window. `None` for `latest`
+    pub fn hours(self) -> Option<f64> {
+        match self {
+            Self::Latest => None,
+            Self::FiveMinute => Some(5.0 / 60.0),
+            Self::OneHour => Some(1.0),
+            Self::TwentyFourHour => Some(24.0),
+        }
+    }
+}
+
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+            Self::FiveMinute => write!(f, "5m"),
+            Self::OneHour => write!(f, "1h"),
+            Self::TwentyFourHour => write!(f, "24h"),
+        }
+    }
+}
+
+/// Interval between points of a single item's timeseries
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeStep {
+    #[serde(rename = "5m")]
+    FiveMinute,
+    #[default]
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "6h")]
+    SixHour,
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+impl fmt::Display for TimeStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FiveMinute => write!(f, "5m"),
+            Self::OneHour => write!(f, "1h"),
+            Self::SixHour => write!(f, "6h"),
+            Self::TwentyFourHour => write!(f, "24h"),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -33,6 +83,51 @@ pub struct Api {
     pub url: String,
     pub timespan: TimeSpan,
     pub auth_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub history: HistoryRetention,
+    /// When to request new prices. The `--refresh` flag always does
+    #[serde(default)]
+    pub refresh: RefreshPolicy,
+    /// Extra attempts after a failed request
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled after each further attempt
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Time allowed for each request
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// When stored prices are replaced with new ones from the api.
+/// `always`, `never`, or `older_than: <minutes>`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RefreshPolicy {
+    Always,
+    #[default]
+    Never,
+    /// Minutes since prices were last requested
+    OlderThan(u32),
+}
+
+impl RefreshPolicy {
+    /// `last_checked` is the unix time prices were last requested, if ever
+    pub fn should_refresh(&self, last_checked: Option<i64>, now: i64) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::OlderThan(mins) => last_checked
+                .is_none_or(|checked| now.saturating_sub(checked) > i64::from(*mins) * 60),
+        }
+    }
+}
+
+/// Limits on the number of stored price snapshots.
+/// `None` means no limit.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct HistoryRetention {
+    pub max_snapshots: Option<usize>,
+    pub max_age_days: Option<u32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -41,17 +136,41 @@ pub struct LookupDataPaths {
     pub name_to_id: String,
     pub api_mapping: String,
     pub recipes: String,
+    /// See [`crate::prices::tax::TaxRules`]
+    #[serde(default = "default_ge_tax")]
+    pub ge_tax: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ResultsPaths {
     pub optimal: String,
     pub lookup: String,
+    #[serde(default = "default_backtest_results")]
+    pub backtest: String,
+    /// Directory for rendered charts
+    #[serde(default = "default_charts_results")]
+    pub charts: String,
+    #[serde(default = "default_allocation_results")]
+    pub allocation: String,
+    #[serde(default = "default_simulation_results")]
+    pub simulation: String,
+    #[serde(default = "default_flips_results")]
+    pub flips: String,
+    #[serde(default = "default_chains_results")]
+    pub chains: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct FilePaths {
     pub price_data: String,
+    #[serde(default = "default_price_history")]
+    pub price_history: String,
+    /// Fetch time and cache validators of `price_data`
+    #[serde(default = "default_price_metadata")]
+    pub price_metadata: String,
+    /// Purchases counted against buy limits. See [`crate::prices::buy_limits::PurchaseLedger`]
+    #[serde(default = "default_buy_ledger")]
+    pub buy_ledger: String,
     pub lookup_data: LookupDataPaths,
     pub results: ResultsPaths,
     pub main_log_file: String,
@@ -68,31 +187,227 @@ pub struct Weights {
 #[derive(Deserialize, Debug)]
 pub struct Profit {
     #[serde(deserialize_with = "deserialize_underscored_integer")]
-    pub coins: i32,
+    pub coins: Gp,
     #[serde(deserialize_with = "assert_positive_f32")]
     pub percent_margin: f32,
     #[serde(rename = "custom_weights")]
     pub weights: Weights,
     pub ignore_items: Vec<String>,
     pub ignore_methods: Vec<String>,
+    #[serde(default)]
+    pub stale_prices: StalePrices,
+    #[serde(default)]
+    pub pricing: PricingStrategy,
+    /// Hours played in a session. Recipes are made for at most this long
+    #[serde(default = "default_session_hours", deserialize_with = "assert_positive_f32")]
+    pub session_hours: f32,
+    #[serde(default)]
+    pub allocation: AllocationOptions,
+    /// Disabled when not set
+    #[serde(default)]
+    pub liquidity: Option<Liquidity>,
+    /// Item name -> price used instead of the loaded one
+    #[serde(default)]
+    pub price_overrides: HashMap<String, PriceOverride>,
+    #[serde(default)]
+    pub flips: FlipOptions,
+    /// Generate a high alchemy recipe for each alchable item
+    #[serde(default)]
+    pub high_alchemy: bool,
+}
+
+/// Filters for the `flips` command. See [`crate::prices::flips::scan`]
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct FlipOptions {
+    /// Fewest items traded per hour, on each side. Unfiltered when not set
+    #[serde(default)]
+    pub min_hourly_volume: Option<f64>,
+    /// Skip items whose high or low price last traded longer ago than this
+    pub max_price_age_mins: u32,
+    /// Averaged timespan the traded volumes are read from
+    #[serde(default = "default_flip_volume")]
+    pub volume: TimeSpan,
+}
+
+/// Replaces the high and low price of an item, to ask "what if" it traded at this price.
+/// Written as `high/low`, or a single price for both
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "RawPriceOverride")]
+pub struct PriceOverride {
+    pub high: i32,
+    pub low: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPriceOverride {
+    Both(i32),
+    Split(String),
+}
+
+impl TryFrom<RawPriceOverride> for PriceOverride {
+    type Error = String;
+
+    fn try_from(raw: RawPriceOverride) -> Result<Self, Self::Error> {
+        match raw {
+            RawPriceOverride::Both(price) => Ok(Self { high: price, low: price }),
+            RawPriceOverride::Split(s) => s.parse(),
+        }
+    }
+}
+
+impl std::str::FromStr for PriceOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_price = |price: &str| price.trim().replace(['_', ','], "").parse::<i32>()
+            .map_err(|_| format!("`{price}` is not a price"));
+
+        match s.split_once('/') {
+            Some((high, low)) => Ok(Self { high: parse_price(high)?, low: parse_price(low)? }),
+            None => parse_price(s).map(|price| Self { high: price, low: price }),
+        }
+    }
+}
+
+impl fmt::Display for PriceOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.high, self.low)
+    }
+}
+
+/// Parse `Item=high/low` from the CLI
+/// # Errors
+/// Errors if there is no `=` or the price is invalid. See [`PriceOverride`]
+pub fn parse_price_override(s: &str) -> Result<(String, PriceOverride), String> {
+    let (item, price) = s.split_once('=')
+        .ok_or_else(|| format!("`{s}` is not of the form Item=high/low"))?;
+    Ok((item.trim().to_string(), price.parse()?))
+}
+
+/// Cap recipes by how much of each item is traded
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Liquidity {
+    /// Averaged timespan the traded volumes are read from
+    pub volume: TimeSpan,
+    /// Largest share of an item's traded volume our offers can expect to fill
+    #[serde(deserialize_with = "assert_positive_f32")]
+    pub max_fraction: f32,
+}
+
+/// Limits for splitting `coins` across several recipes.
+/// See [`crate::prices::allocation::plan`]
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AllocationOptions {
+    pub hours: f32,
+    pub ge_slots: u32,
+}
+
+/// Which prices items are bought and sold at
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[clap(rename_all = "snake_case")]
+pub enum PricingStrategy {
+    /// Buy at the high price, sell at the low price
+    #[default]
+    Instant,
+    /// Buy at the low price, sell at the high price
+    Patient,
+    /// Halfway between the high and low price
+    Midpoint,
+    /// High and low prices weighted by their traded volume
+    VolumeWeighted,
+}
+
+impl PricingStrategy {
+    /// Sides of the price a trade uses (true means high)
+    pub fn sides(self, buying: bool) -> &'static [bool] {
+        match (self, buying) {
+            (Self::Instant, true) | (Self::Patient, false) => &[true],
+            (Self::Instant, false) | (Self::Patient, true) => &[false],
+            (Self::Midpoint | Self::VolumeWeighted, _) => &[true, false],
+        }
+    }
+}
+
+impl fmt::Display for PricingStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Instant => "instant",
+            Self::Patient => "patient",
+            Self::Midpoint => "midpoint",
+            Self::VolumeWeighted => "volume_weighted",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Maximum age (in minutes) of the last trade on each side of an item's price.
+/// Ages are relative to the most recent trade in the loaded prices.
+/// `None` means no limit.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct StalePrices {
+    pub max_high_age_mins: Option<u32>,
+    pub max_low_age_mins: Option<u32>,
+    #[serde(default)]
+    pub action: StaleAction,
+}
+
+/// What to do with recipes priced from stale items
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StaleAction {
+    /// Mark the price age and list after fresh recipes
+    #[default]
+    Flag,
+    /// Skip the recipe
+    Exclude,
+}
+
+impl StalePrices {
+    /// Whether a price on the given side, last traded `age` seconds ago, is too old
+    pub fn is_stale(&self, high_price: bool, age: i64) -> bool {
+        let max_age_mins = if high_price { self.max_high_age_mins } else { self.max_low_age_mins };
+        max_age_mins.is_some_and(|mins| age > i64::from(mins) * 60)
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct LookupOptions {
     pub top: u32,
     pub specific: Vec<String>,
+    /// Show recent price history for each item when set
+    #[serde(default)]
+    pub timeseries: Option<TimeStep>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 pub enum Membership {
     #[serde(rename = "f2p")]
     F2P,
     #[serde(rename = "p2p")]
     P2P,
     #[serde(rename = "both")]
+    #[default]
     BOTH,
 }
 
+impl Membership {
+    /// Whether a recipe should be skipped for its membership requirement
+    pub fn excludes(&self, needs_members: bool) -> bool {
+        match self {
+            Self::F2P => needs_members,
+            Self::P2P => !needs_members,
+            Self::BOTH => false,
+        }
+    }
+
+    /// Whether an item can't be traded. Members can trade every item, unlike [`Membership::excludes`]
+    pub fn excludes_item(&self, members_item: bool) -> bool {
+        matches!(self, Self::F2P) && members_item
+    }
+}
+
 #[derive(Deserialize, Clone, Copy, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum OverviewSortBy {
@@ -123,7 +438,10 @@ pub struct Display {
     pub sort_by: OverviewSortBy,
     pub filters: EnumMap<OverviewFilter, bool>,
     pub membership: Membership,
-    pub time_type: TimeType,
+    /// Render price charts from the stored price history
+    pub charts: bool,
+    /// Also render recipe profit charts, which backtest every stored snapshot. Needs `charts`
+    pub profit_charts: bool,
 }
 
 #[derive(Debug)]
@@ -177,6 +495,20 @@ impl Default for Api {
                 .into_iter()
                 .map(|(k, v)| (k.to_string(), v.to_string()))
                 .collect(),
+            history: HistoryRetention::default(),
+            refresh: RefreshPolicy::default(),
+            max_retries: default_max_retries(),
+            backoff_ms: default_backoff_ms(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_snapshots: Some(500),
+            max_age_days: Some(30),
         }
     }
 }
@@ -187,6 +519,7 @@ impl Default for LookupDataPaths {
             id_to_name: "lookup_data/id_to_name.yaml".to_string(),
             name_to_id: "lookup_data/name_to_id.yaml".to_string(),
             recipes: "lookup_data/recipes.yaml".to_string(),
+            ge_tax: default_ge_tax(),
             // External file
             api_mapping: "lookup_data/mapping.json".to_string(),
         }
@@ -197,6 +530,12 @@ impl Default for ResultsPaths {
         Self {
             optimal: "results/optimal_overview.md".to_string(),
             lookup: "results/recipe_lookup.md".to_string(),
+            backtest: default_backtest_results(),
+            charts: default_charts_results(),
+            allocation: default_allocation_results(),
+            simulation: default_simulation_results(),
+            flips: default_flips_results(),
+            chains: default_chains_results(),
         }
     }
 }
@@ -204,6 +543,9 @@ impl Default for FilePaths {
     fn default() -> Self {
         Self {
             price_data: "api_data/price_data.json".to_string(),
+            price_history: default_price_history(),
+            price_metadata: default_price_metadata(),
+            buy_ledger: default_buy_ledger(),
             lookup_data: LookupDataPaths::default(),
             results: ResultsPaths::default(),
             main_log_file: "runtime.log".to_string(),
@@ -212,6 +554,25 @@ impl Default for FilePaths {
     }
 }
 
+impl Default for FlipOptions {
+    fn default() -> Self {
+        Self {
+            min_hourly_volume: None,
+            max_price_age_mins: 60,
+            volume: default_flip_volume(),
+        }
+    }
+}
+
+impl Default for AllocationOptions {
+    fn default() -> Self {
+        Self {
+            hours: 6.0,
+            ge_slots: 8,
+        }
+    }
+}
+
 impl Default for Weights {
     fn default() -> Self {
         Self {
@@ -224,11 +585,19 @@ impl Default for Weights {
 impl Default for Profit {
     fn default() -> Self {
         Self {
-            coins: 2_000_000,
+            coins: Gp::new(2_000_000),
             percent_margin: 2.5,
             weights: Weights::default(),
             ignore_items: vec![],
             ignore_methods: vec![],
+            stale_prices: StalePrices::default(),
+            pricing: PricingStrategy::default(),
+            session_hours: default_session_hours(),
+            allocation: AllocationOptions::default(),
+            liquidity: None,
+            price_overrides: HashMap::new(),
+            flips: FlipOptions::default(),
+            high_alchemy: false,
         }
     }
 }
@@ -238,14 +607,10 @@ impl Default for LookupOptions {
         Self {
             top: 3,
             specific: Vec::new(),
+            timeseries: None,
         }
     }
 }
-impl Default for Membership {
-    fn default() -> Self {
-        Self::BOTH
-    }
-}
 impl Default for Display {
     fn default() -> Self {
         Self {
@@ -258,7 +623,8 @@ impl Default for Display {
                 OverviewFilter::Reverse => true,
             },
             membership: Membership::default(),
-            time_type: TimeType::default(),
+            charts: false,
+            profit_charts: false,
         }
     }
 }
@@ -324,8 +690,68 @@ pub fn load_config<P: AsRef<std::path::Path>>(filepath: P) -> Config {
         .unwrap_or_else(|e| panic!("{e:?}"))
 }
 
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_backoff_ms() -> u64 {
+    500
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_price_history() -> String {
+    "api_data/history".to_string()
+}
+
+fn default_price_metadata() -> String {
+    "api_data/price_metadata.yaml".to_string()
+}
+
+fn default_session_hours() -> f32 {
+    DEFAULT_SESSION_HOURS
+}
+
+fn default_buy_ledger() -> String {
+    "api_data/buy_ledger.yaml".to_string()
+}
+
+fn default_allocation_results() -> String {
+    "results/allocation.md".to_string()
+}
+
+fn default_simulation_results() -> String {
+    "results/simulation.md".to_string()
+}
+
+fn default_flips_results() -> String {
+    "results/flips.md".to_string()
+}
+
+fn default_chains_results() -> String {
+    "results/chains.md".to_string()
+}
+
+fn default_flip_volume() -> TimeSpan {
+    TimeSpan::TwentyFourHour
+}
+
+fn default_ge_tax() -> String {
+    "lookup_data/ge_tax.yaml".to_string()
+}
+
+fn default_backtest_results() -> String {
+    "results/backtest.md".to_string()
+}
+
+fn default_charts_results() -> String {
+    "results/charts".to_string()
+}
+
 /// To parse underscored integer representaions
-fn deserialize_underscored_integer<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+pub(crate) fn deserialize_underscored_integer<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: serde::de::Deserializer<'de>,
     T: std::str::FromStr,
@@ -349,7 +775,7 @@ where
 
 
     let error = serde::de::Error::custom(format!(
-        "`percent_margin` must be positive, got {value}",
+        "Expected a positive number, got {value}",
     ));
     
     Err(error)
@@ -360,7 +786,8 @@ where
 use serde::{de::Visitor, Deserializer};
 use std::fmt;
 
-use crate::prices::prices::TimeType;
+use crate::gp::Gp;
+use crate::prices::prices::DEFAULT_SESSION_HOURS;
 
 impl<'de> Deserialize<'de> for Display {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -381,6 +808,7 @@ A LookupOptions for `lookup`
 An OverviewSortBy for `sort_by`
 Up to 3 fields of bool corresponding to whether each of the display `filters` is enabled.
 A bool to show if methods requiring `membership` should be shown.
+Optionally, bools to render `charts` and `profit_charts` from the price history.
                     ")
             }
 
@@ -412,13 +840,25 @@ A bool to show if methods requiring `membership` should be shown.
                 let (_, membership) = map.next_entry::<String, Membership>()?
                     .expect("Failed to deserialize membership");
 
+                // Optional keys
+                let mut charts = false;
+                let mut profit_charts = false;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "charts" => charts = map.next_value()?,
+                        "profit_charts" => profit_charts = map.next_value()?,
+                        other => return Err(serde::de::Error::unknown_field(other, &["charts", "profit_charts"])),
+                    }
+                }
+
                 let final_display = Display {
                     number,
                     lookup,
                     sort_by,
                     filters: filter_map,
                     membership,
-                    time_type: TimeType::default(), // Not a file config parameter
+                    charts,
+                    profit_charts,
                 };
 
                 Ok(final_display)
@@ -428,6 +868,49 @@ A bool to show if methods requiring `membership` should be shown.
     }
 }
 
+impl<'de> Deserialize<'de> for RefreshPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RefreshPolicyVisitor;
+
+        impl<'de> Visitor<'de> for RefreshPolicyVisitor {
+            type Value = RefreshPolicy;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("`always`, `never` or `older_than: <minutes>`")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                match v {
+                    "always" => Ok(RefreshPolicy::Always),
+                    "never" => Ok(RefreshPolicy::Never),
+                    other => Err(E::unknown_variant(other, &["always", "never", "older_than"])),
+                }
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let Some((key, mins)) = map.next_entry::<String, u32>()? else {
+                    return Err(serde::de::Error::missing_field("older_than"));
+                };
+                if key != "older_than" {
+                    return Err(serde::de::Error::unknown_field(&key, &["older_than"]));
+                }
+
+                Ok(RefreshPolicy::OlderThan(mins))
+            }
+        }
+        deserializer.deserialize_any(RefreshPolicyVisitor)
+    }
+}
+
 impl<'de> Deserialize<'de> for Levels {
     // #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -474,3 +957,34 @@ impl<'de> Deserialize<'de> for Levels {
         deserializer.deserialize_map(LevelsVisitor)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn refresh_policy() {
+        let parse = |s: &str| serde_yaml_ng::from_str::<RefreshPolicy>(s);
+        assert_eq!(parse("always").unwrap(), RefreshPolicy::Always);
+        assert_eq!(parse("never").unwrap(), RefreshPolicy::Never);
+        assert_eq!(parse("older_than: 30").unwrap(), RefreshPolicy::OlderThan(30));
+        assert!(parse("sometimes").is_err());
+        assert!(parse("newer_than: 30").is_err());
+
+        let policy = RefreshPolicy::OlderThan(30);
+        assert!(policy.should_refresh(None, 10_000));
+        assert!(policy.should_refresh(Some(10_000 - 31 * 60), 10_000));
+        assert!(!policy.should_refresh(Some(10_000 - 29 * 60), 10_000));
+    }
+
+    #[test]
+    fn price_override_formats() {
+        let parse = |s: &str| serde_yaml_ng::from_str::<PriceOverride>(s);
+        assert_eq!(parse("120").unwrap(), PriceOverride { high: 120, low: 120 });
+        assert_eq!(parse("1_200/1,150").unwrap(), PriceOverride { high: 1_200, low: 1_150 });
+        assert!(parse("high/low").is_err());
+
+        assert_eq!(parse_price_override("Astral rune=120/115"), Ok(("Astral rune".to_string(), PriceOverride { high: 120, low: 115 })));
+        assert!(parse_price_override("Astral rune").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Amounts of coins (GP)
+//!
+//! Rounding of fractional amounts is always explicit. See [`Rounding`]:
+//! - Amounts paid (inputs, costs) round [up](Rounding::Up)
+//! - Amounts received (outputs, tax taken, GP/h) round [down](Rounding::Down)
+//!
+//! So any estimate errs on the side of less profit.
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use crate::helpers::ToCommaString;
+
+/// An amount of coins. Backed by `i64`, so totals over a large bankroll cannot overflow.
+/// Arithmetic is checked, and panics on overflow instead of wrapping or saturating.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gp(i64);
+
+/// How a fractional amount of coins becomes whole coins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Towards negative infinity
+    Down,
+    /// Towards positive infinity
+    Up,
+    /// Half away from zero
+    Nearest,
+}
+
+impl Rounding {
+    fn apply(self, value: f64) -> f64 {
+        match self {
+            Self::Down => value.floor(),
+            Self::Up => value.ceil(),
+            Self::Nearest => value.round(),
+        }
+    }
+}
+
+impl Gp {
+    pub const ZERO: Self = Self(0);
+    pub const MAX: Self = Self(i64::MAX);
+
+    pub const fn new(coins: i64) -> Self {
+        Self(coins)
+    }
+
+    pub const fn coins(self) -> i64 {
+        self.0
+    }
+
+    /// Whole coins from a fractional amount.
+    /// Saturates at the `i64` limits, and `NaN` is zero.
+    pub fn from_f64(coins: f64, rounding: Rounding) -> Self {
+        #[allow(clippy::cast_possible_truncation)]
+        // SAFETY: float to int casts saturate
+        Self(rounding.apply(coins) as i64)
+    }
+
+    /// Exact for amounts below 2^53 coins
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64
+    }
+
+    /// `self` multiplied by a fractional `factor`, e.g. a quantity of 0.5 or a 1.5% margin
+    #[must_use]
+    pub fn mul_f64(self, factor: f64, rounding: Rounding) -> Self {
+        Self::from_f64(self.to_f64() * factor, rounding)
+    }
+
+    /// # Panics
+    /// On overflow
+    #[must_use]
+    pub fn times(self, number: i64) -> Self {
+        Self(self.0.checked_mul(number).expect("GP overflow"))
+    }
+
+    /// Number of whole `price`s within `self`.
+    /// `None` when `price` is not positive.
+    pub fn affordable(self, price: Self) -> Option<i64> {
+        (price.0 > 0).then(|| self.0.div_euclid(price.0))
+    }
+
+    /// Rate per hour of earning `self` in `seconds`, rounded down
+    #[must_use]
+    pub fn per_hour(self, seconds: f64) -> Self {
+        Self::from_f64(self.to_f64() * 3600.0 / seconds, Rounding::Down)
+    }
+}
+
+impl From<i32> for Gp {
+    fn from(coins: i32) -> Self {
+        Self(i64::from(coins))
+    }
+}
+
+impl Add for Gp {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.checked_add(rhs.0).expect("GP overflow"))
+    }
+}
+
+impl Sub for Gp {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.checked_sub(rhs.0).expect("GP overflow"))
+    }
+}
+
+impl Neg for Gp {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.checked_neg().expect("GP overflow"))
+    }
+}
+
+impl AddAssign for Gp {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Gp {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for Gp {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+impl FromStr for Gp {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+/// Comma separated, e.g. `2,700,000`
+impl fmt::Display for Gp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.to_comma_sep_string())
+    }
+}
+
+impl ToCommaString for Gp {
+    fn to_comma_sep_string(self) -> String {
+        self.0.to_comma_sep_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rounding_and_overflow() {
+        let price = Gp::new(1_001);
+        assert_eq!(price.mul_f64(0.5, Rounding::Down), Gp::new(500));
+        assert_eq!(price.mul_f64(0.5, Rounding::Up), Gp::new(501));
+        assert_eq!(Gp::new(-7).mul_f64(0.5, Rounding::Down), Gp::new(-4));
+
+        // Well above i32::MAX
+        let bankroll: Gp = "10000000000".parse().unwrap();
+        assert_eq!(bankroll.affordable(price), Some(9_990_009));
+        assert_eq!(bankroll.affordable(Gp::ZERO), None);
+        assert_eq!(bankroll.to_string(), "10,000,000,000");
+
+        assert!(std::panic::catch_unwind(|| Gp::MAX + Gp::new(1)).is_err());
+    }
+}
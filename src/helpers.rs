@@ -1,5 +1,7 @@
 use std::io::Write;
 
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
 #[allow(clippy::cast_possible_truncation)]
 pub fn floor(x: f64) -> i32 {
     x.floor() as i32
@@ -31,3 +33,48 @@ impl<T: num_format::ToFormattedStr> ToCommaString for T {
     }
 }
 
+
+/// Parse a unix timestamp, or a UTC date (`YYYY-MM-DD`)
+/// or date-time (`YYYY-MM-DDTHH:MM[:SS]`) into a unix timestamp.
+/// # Errors
+/// Errors if `s` does not match any of the formats.
+pub fn parse_timestamp(s: &str) -> Result<i64, String> {
+    if let Ok(unix) = s.parse::<i64>() {
+        return Ok(unix);
+    }
+
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(s, format) {
+            return Ok(datetime.and_utc().timestamp());
+        }
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc().timestamp())
+        .ok_or_else(|| format!("`{s}` is not a unix timestamp, YYYY-MM-DD or YYYY-MM-DDTHH:MM"))
+}
+
+/// Format a unix timestamp as a UTC date-time
+pub fn format_timestamp(unix: i64) -> String {
+    DateTime::from_timestamp(unix, 0)
+        .map_or_else(|| unix.to_string(), |t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+/// Short duration from seconds, e.g. `45m`, `6h` or `3d`
+pub fn format_age(secs: i64) -> String {
+    const SEC_IN_MIN: i64 = 60;
+    const SEC_IN_HOUR: i64 = 60 * SEC_IN_MIN;
+    const SEC_IN_DAY: i64 = 24 * SEC_IN_HOUR;
+
+    match secs {
+        s if s < SEC_IN_HOUR => format!("{}m", s / SEC_IN_MIN),
+        s if s < SEC_IN_DAY => format!("{}h", s / SEC_IN_HOUR),
+        s => format!("{}d", s / SEC_IN_DAY),
+    }
+}
+
+pub fn unix_now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
@@ -15,11 +15,16 @@ pub mod latest {
         pub high_time: Option<i32>, // Unix Timestamp
         pub low: Option<i32>,
         pub low_time: Option<i32>,
+        // Only set when converted from an averaged (`oldest`) timespan
+        pub high_volume: Option<i32>,
+        pub low_volume: Option<i32>,
     }
 
     #[derive(Debug, Clone, Default)]
     pub struct PriceDataType {
         pub data: HashMap<String, PriceDatum>,
+        /// Start of the averaged window when converted from an averaged (`oldest`) timespan
+        pub timestamp: Option<i32>,
     }
 
     impl<'de> Deserialize<'de> for PriceDatum {
@@ -34,7 +39,7 @@ pub mod latest {
                 type Value = PriceDatum;
 
                 fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                    formatter.write_str("four fields containting values/nones: i32,u32,i32,u32.")
+                    formatter.write_str("four (or six) fields containting values/nones: i32,u32,i32,u32(,i32,i32).")
                 }
 
                 fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -53,6 +58,9 @@ pub mod latest {
                         high_time: datum["highTime"],
                         low: datum["low"],
                         low_time: datum["lowTime"],
+                        // Volumes are missing for `latest` data
+                        high_volume: datum.get("highPriceVolume").copied().flatten(),
+                        low_volume: datum.get("lowPriceVolume").copied().flatten(),
                     })
                 }
             }
@@ -81,24 +89,18 @@ pub mod latest {
                 where
                     A: serde::de::MapAccess<'de>,
                 {
-                    // While there are entries remaining in the input, add them
-                    // into our map.
-                    let mut api_data =
-                        HashMap::<String, HashMap<String, PriceDatum>>::with_capacity(
-                            map.size_hint().unwrap_or(0),
-                        );
-                    while let Some((key, value)) = map.next_entry()? {
-                        api_data.insert(key, value); // Should just insert "data" key
-                    }
-
-                    let a_data = api_data["data"].clone().into_iter();
-                    let mut data =
-                        HashMap::<String, PriceDatum>::with_capacity(api_data["data"].capacity());
-                    for (key, value) in a_data {
-                        data.insert(key, value); // Inserting each "id" => {...}
+                    let mut data: Option<HashMap<String, PriceDatum>> = None;
+                    let mut timestamp: Option<i32> = None;
+                    while let Some(key) = map.next_key::<String>()? {
+                        match key.as_str() {
+                            "data" => data = Some(map.next_value()?), // Each "id" => {...}
+                            "timestamp" => timestamp = map.next_value()?,
+                            _ => { map.next_value::<serde::de::IgnoredAny>()?; },
+                        }
                     }
 
-                    Ok(PriceDataType { data })
+                    let data = data.ok_or_else(|| serde::de::Error::missing_field("data"))?;
+                    Ok(PriceDataType { data, timestamp })
                 }
             }
             deserializer.deserialize_map(PriceDataTypeVisitor)
@@ -111,12 +113,20 @@ pub mod latest {
         where
             S: serde::Serializer,
         {
-            let mut state = serializer.serialize_struct("PriceDatum", 4)?;
-            // TODO: This will error when switching to volume
+            let mut state = serializer.serialize_struct("PriceDatum", 6)?;
             state.serialize_field::<Option<i32>>("high", &self.high)?;
             state.serialize_field::<Option<i32>>("highTime", &self.high_time)?;
             state.serialize_field::<Option<i32>>("low", &self.low)?;
             state.serialize_field::<Option<i32>>("lowTime", &self.low_time)?;
+
+            // Keep `latest` files the same shape as the api response
+            if self.high_volume.is_some() || self.low_volume.is_some() {
+                state.serialize_field::<Option<i32>>("highPriceVolume", &self.high_volume)?;
+                state.serialize_field::<Option<i32>>("lowPriceVolume", &self.low_volume)?;
+            } else {
+                state.skip_field("highPriceVolume")?;
+                state.skip_field("lowPriceVolume")?;
+            }
             state.end()
         }
     }
@@ -126,8 +136,13 @@ pub mod latest {
         where
             S: serde::Serializer,
         {
-            let mut state = serializer.serialize_struct("PriceDataType", 1)?;
+            let mut state = serializer.serialize_struct("PriceDataType", 2)?;
             state.serialize_field::<HashMap<String, PriceDatum>>("data", &self.data)?;
+            if let Some(timestamp) = self.timestamp {
+                state.serialize_field::<i32>("timestamp", &timestamp)?;
+            } else {
+                state.skip_field("timestamp")?;
+            }
             state.end()
         }
     }
@@ -150,5 +165,173 @@ pub mod latest {
     }
 }
 
-/// TODO
-pub mod oldest {}
+/// For use with the averaged `5m` and `1h` timespans
+pub mod oldest {
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    use super::latest;
+
+    /// Average prices over the timespan window.
+    /// Prices are `None` when no trades were made on that side during the window.
+    #[derive(Debug, Clone, Default, Copy, Deserialize, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PriceDatum {
+        pub avg_high_price: Option<i32>,
+        #[serde(default)]
+        pub high_price_volume: Option<i32>,
+        pub avg_low_price: Option<i32>,
+        #[serde(default)]
+        pub low_price_volume: Option<i32>,
+    }
+
+    #[derive(Debug, Clone, Default, Deserialize, Serialize)]
+    pub struct PriceDataType {
+        pub data: HashMap<String, PriceDatum>,
+        /// Unix timestamp for the start of the averaged window
+        pub timestamp: i32,
+    }
+
+    impl PriceDatum {
+        /// Convert into the common price format.
+        /// The window `timestamp` is used as the trade time for each side with a price.
+        #[must_use]
+        pub fn to_latest(self, timestamp: i32) -> latest::PriceDatum {
+            latest::PriceDatum {
+                high: self.avg_high_price,
+                high_time: self.avg_high_price.map(|_| timestamp),
+                low: self.avg_low_price,
+                low_time: self.avg_low_price.map(|_| timestamp),
+                high_volume: self.high_price_volume,
+                low_volume: self.low_price_volume,
+            }
+        }
+    }
+
+    impl From<PriceDataType> for latest::PriceDataType {
+        fn from(value: PriceDataType) -> Self {
+            let timestamp = value.timestamp;
+            let data = value.data
+                .into_iter()
+                .map(|(id, datum)| (id, datum.to_latest(timestamp)))
+                .collect();
+
+            latest::PriceDataType { data, timestamp: Some(timestamp) }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        /// Averaged prices keep their volumes and window, and sides without trades stay invalid
+        fn convert_averaged_prices() {
+            let response = r#"{"data":{"2":{"avgHighPrice":180,"highPriceVolume":1200,"avgLowPrice":null,"lowPriceVolume":0}},"timestamp":1776380400}"#;
+            let averaged: PriceDataType = serde_yaml_ng::from_str(response).unwrap();
+            let converted = latest::PriceDataType::from(averaged);
+
+            assert_eq!(converted.timestamp, Some(1_776_380_400));
+            let datum = converted.data["2"];
+            assert_eq!(datum.high, Some(180));
+            assert_eq!(datum.high_time, Some(1_776_380_400));
+            assert_eq!(datum.high_volume, Some(1200));
+            assert_eq!(datum.low, None);
+            assert_eq!(datum.low_volume, Some(0));
+            assert!(datum.invalid_data());
+
+            // Stored prices keep the window
+            let stored = serde_json::to_string(&converted).unwrap();
+            let loaded: latest::PriceDataType = serde_json::from_str(&stored).unwrap();
+            assert_eq!(loaded.timestamp, Some(1_776_380_400));
+        }
+    }
+}
+
+/// For use with the `timeseries` endpoint of a single item
+pub mod timeseries {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Default, Copy, Deserialize, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TimeseriesDatum {
+        pub timestamp: i32, // Unix Timestamp
+        pub avg_high_price: Option<i32>,
+        pub avg_low_price: Option<i32>,
+        #[serde(default)]
+        pub high_price_volume: Option<i32>,
+        #[serde(default)]
+        pub low_price_volume: Option<i32>,
+    }
+
+    #[derive(Debug, Clone, Default, Deserialize, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TimeseriesDataType {
+        pub data: Vec<TimeseriesDatum>,
+        #[serde(default)]
+        pub item_id: Option<i32>,
+    }
+
+    /// Spread of an item's price over the returned timeseries
+    #[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
+    pub struct PriceSummary {
+        pub min: i32,
+        pub max: i32,
+        pub mean: i32,
+    }
+
+    impl TimeseriesDatum {
+        pub fn price(&self, high_price: bool) -> Option<i32> {
+            if high_price {
+                self.avg_high_price
+            } else {
+                self.avg_low_price
+            }
+        }
+    }
+
+    impl TimeseriesDataType {
+        /// Min, max and mean of the high or low prices.
+        /// Points without trades on that side are ignored.
+        /// Returns `None` if there are no valid points.
+        #[must_use]
+        pub fn summary(&self, high_price: bool) -> Option<PriceSummary> {
+            let prices: Vec<i64> = self.data
+                .iter()
+                .filter_map(|d| d.price(high_price))
+                .map(i64::from)
+                .collect();
+
+            let min = *prices.iter().min()?;
+            let max = *prices.iter().max()?;
+            let count = i64::try_from(prices.len()).ok()?;
+            let mean = prices.iter().sum::<i64>() / count;
+
+            Some(PriceSummary {
+                min: i32::try_from(min).ok()?,
+                max: i32::try_from(max).ok()?,
+                mean: i32::try_from(mean).ok()?,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        /// Points without trades on a side are not counted in the summary
+        fn summary_skips_missing_prices() {
+            let response = r#"{"data":[
+                {"timestamp":1,"avgHighPrice":100,"avgLowPrice":null,"highPriceVolume":5,"lowPriceVolume":0},
+                {"timestamp":2,"avgHighPrice":130,"avgLowPrice":90,"highPriceVolume":5,"lowPriceVolume":3},
+                {"timestamp":3,"avgHighPrice":null,"avgLowPrice":96,"highPriceVolume":0,"lowPriceVolume":3}
+            ],"itemId":2}"#;
+            let timeseries: TimeseriesDataType = serde_yaml_ng::from_str(response).unwrap();
+
+            assert_eq!(timeseries.item_id, Some(2));
+            assert_eq!(timeseries.summary(true), Some(PriceSummary { min: 100, max: 130, mean: 115 }));
+            assert_eq!(timeseries.summary(false), Some(PriceSummary { min: 90, max: 96, mean: 93 }));
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Local store of previous price data snapshots
+//! Each snapshot is stored as its own file, named by the unix time it was fetched at.
+//! Inside each snapshot prices are keyed by item id (See [`PriceDataType`]).
+//! [`PriceHistory`] reads a range of snapshots once and indexes them by item id
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+use tracing::{debug, instrument, trace, warn};
+
+use crate::config::HistoryRetention;
+use crate::file_io::{FileIO, FileOptions, SerChoice};
+
+use super::data_types::latest::{PriceDataType, PriceDatum};
+
+const SNAPSHOT_EXTENSION: &str = "yaml";
+const SEC_IN_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    directory: PathBuf,
+    retention: HistoryRetention,
+}
+
+impl HistoryStore {
+    pub fn new<P: Into<PathBuf>>(directory: P, retention: HistoryRetention) -> Self {
+        Self {
+            directory: directory.into(),
+            retention,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn snapshot_path(&self, fetched_at: i64) -> PathBuf {
+        self.directory.join(format!("{fetched_at}.{SNAPSHOT_EXTENSION}"))
+    }
+
+    /// Store a new snapshot, then remove snapshots outside of the retention limits.
+    /// # Errors
+    /// Errors if the directory can not be created or the snapshot fails to be written.
+    #[instrument(level = "debug", skip(self, prices))]
+    pub fn append(&self, fetched_at: i64, prices: &PriceDataType) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.snapshot_path(fetched_at);
+        let mut snapshot_fio = FileIO::new(
+            path.to_string_lossy().into_owned(),
+            FileOptions::new(true, true, true),
+        );
+        snapshot_fio.clear_contents()?;
+        snapshot_fio.write_serialized(prices)?;
+        debug!(desc = "Stored price snapshot", path = %path.display());
+
+        let removed = self.prune(fetched_at)?;
+        debug!(desc = "Pruned price snapshots", count = removed);
+
+        Ok(())
+    }
+
+    /// Fetch times of all stored snapshots, oldest first.
+    /// Returns an empty list when nothing has been stored yet.
+    /// # Errors
+    /// Errors if the directory can not be read.
+    pub fn snapshot_times(&self) -> io::Result<Vec<i64>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut times = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SNAPSHOT_EXTENSION) {
+                continue;
+            }
+
+            if let Some(Ok(time)) = path.file_stem().and_then(|s| s.to_str()).map(str::parse::<i64>) {
+                times.push(time);
+            } else {
+                warn!(desc = "Unexpected file in price history", path = %path.display());
+            }
+        }
+        times.sort_unstable();
+
+        Ok(times)
+    }
+
+    /// # Errors
+    /// Errors if the snapshot does not exist. See [`FileIO::read_serialized`]
+    pub fn load(&self, fetched_at: i64) -> io::Result<PriceDataType> {
+        let path = self.snapshot_path(fetched_at);
+        if !path.exists() {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let mut snapshot_fio = FileIO::new(
+            path.to_string_lossy().into_owned(),
+            FileOptions::new(true, false, false),
+        );
+        snapshot_fio.read_serialized(SerChoice::YAML)
+    }
+
+    /// Latest snapshot fetched at or before `timestamp`
+    /// # Errors
+    /// See [`HistoryStore::snapshot_times`] and [`HistoryStore::load`]
+    pub fn as_of(&self, timestamp: i64) -> io::Result<Option<(i64, PriceDataType)>> {
+        let Some(fetched_at) = self.snapshot_times()?
+            .into_iter()
+            .rev()
+            .find(|&t| t <= timestamp)
+        else {
+            return Ok(None);
+        };
+
+        trace!(desc = "Resolved snapshot", timestamp, fetched_at);
+        self.load(fetched_at).map(|prices| Some((fetched_at, prices)))
+    }
+
+    /// All snapshots fetched within `[from, to]`, each read once and indexed by item id
+    /// # Errors
+    /// See [`HistoryStore::snapshot_times`] and [`HistoryStore::load`]
+    #[instrument(level = "debug", skip(self))]
+    pub fn load_between(&self, from: i64, to: i64) -> io::Result<PriceHistory> {
+        let snapshots = self.snapshot_times()?
+            .into_iter()
+            .filter(|t| (from..=to).contains(t))
+            .map(|t| self.load(t).map(|prices| (t, prices)))
+            .collect::<io::Result<Vec<_>>>()?;
+        debug!(desc = "Loaded price history", snapshots = snapshots.len());
+
+        Ok(PriceHistory::new(snapshots))
+    }
+
+    /// Remove snapshots older than `max_age_days` (relative to `now`),
+    /// then the oldest snapshots above `max_snapshots`.
+    /// Returns the number of snapshots removed.
+    /// # Errors
+    /// Errors if a snapshot fails to be removed.
+    pub fn prune(&self, now: i64) -> io::Result<usize> {
+        let times = self.snapshot_times()?;
+
+        let mut keep: Vec<i64> = times.clone();
+        if let Some(days) = self.retention.max_age_days {
+            let oldest_allowed = now.saturating_sub(i64::from(days).saturating_mul(SEC_IN_DAY));
+            keep.retain(|&t| t >= oldest_allowed);
+        }
+        if let Some(max_snapshots) = self.retention.max_snapshots {
+            let excess = keep.len().saturating_sub(max_snapshots);
+            keep.drain(..excess);
+        }
+
+        let mut removed: usize = 0;
+        for time in times.into_iter().filter(|t| !keep.contains(t)) {
+            fs::remove_file(self.snapshot_path(time))?;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Snapshots read from a [`HistoryStore`], shared by everything charting or backtesting them
+#[derive(Debug, Clone, Default)]
+pub struct PriceHistory {
+    /// Oldest first
+    snapshots: Vec<(i64, PriceDataType)>,
+    /// Item id -> prices across the snapshots it appears in, oldest first
+    by_item: HashMap<String, Vec<(i64, PriceDatum)>>,
+}
+
+impl PriceHistory {
+    /// `snapshots` must be oldest first
+    pub fn new(snapshots: Vec<(i64, PriceDataType)>) -> Self {
+        let mut by_item: HashMap<String, Vec<(i64, PriceDatum)>> = HashMap::new();
+        for (fetched_at, prices) in &snapshots {
+            for (item_id, datum) in &prices.data {
+                by_item.entry(item_id.clone()).or_default().push((*fetched_at, *datum));
+            }
+        }
+
+        Self { snapshots, by_item }
+    }
+
+    pub fn snapshots(&self) -> &[(i64, PriceDataType)] {
+        &self.snapshots
+    }
+
+    /// Prices of a single item, oldest first
+    pub fn item(&self, item_id: &str) -> &[(i64, PriceDatum)] {
+        self.by_item.get(item_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn single_price(price: i32) -> PriceDataType {
+        let mut prices = PriceDataType::default();
+        prices.add_datum("2".to_string(), PriceDatum {
+            high: Some(price),
+            high_time: Some(0),
+            low: Some(price),
+            low_time: Some(0),
+            ..Default::default()
+        });
+        prices
+    }
+
+    #[test]
+    /// Snapshots resolve to the latest one before a time, and the oldest are pruned
+    fn append_resolve_and_prune() {
+        let directory = std::env::temp_dir().join(format!("osrs_gph_history_{}", std::process::id()));
+        let retention = HistoryRetention { max_snapshots: Some(2), max_age_days: None };
+        let store = HistoryStore::new(&directory, retention);
+
+        for (time, price) in [(100, 1), (200, 2), (300, 3)] {
+            store.append(time, &single_price(price)).unwrap();
+        }
+
+        assert_eq!(store.snapshot_times().unwrap(), vec![200, 300]);
+
+        let (fetched_at, prices) = store.as_of(250).unwrap().unwrap();
+        assert_eq!(fetched_at, 200);
+        assert_eq!(prices.data["2"].high, Some(2));
+        assert!(store.as_of(150).unwrap().is_none());
+
+        let history = store.load_between(0, 250).unwrap();
+        assert_eq!(history.snapshots().len(), 1);
+        assert_eq!(history.item("2").iter().map(|(t, datum)| (*t, datum.high)).collect::<Vec<_>>(), vec![(200, Some(2))]);
+        assert!(history.item("3").is_empty());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
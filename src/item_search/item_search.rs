@@ -1,14 +1,18 @@
 //! Parsing items from [api](src/api.rs) data
-//! TODO: Support for different modules (timespans) other than just latest
+//! Averaged timespans are converted into the `latest` format by the [api](src/api.rs)
 use super::data_types::latest::{self, PriceDataType, SPECIAL_ITEM_NAMES}; //::PriceDatum;
+use super::data_types::timeseries::TimeseriesDataType;
+use super::history::HistoryStore;
+use super::price_source::{FileSource, HttpSource, PriceSource};
 
 use tracing::{debug, instrument, warn};
 
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{collections::{HashMap, HashSet}, fmt::Debug, hash::Hash};
 
 use crate::api::MappingItem;
-use crate::config::FilePaths;
+use crate::config::{FilePaths, PriceOverride, PricingStrategy, TimeSpan};
+use crate::gp::{Gp, Rounding};
 use crate::file_io::{FileIO, FileOptions};
 use crate::item_search::data_types::latest::PriceDatum;
 use crate::{file_io, log_match_panic, log_panic};
@@ -58,6 +62,12 @@ impl Item {
         }
     }
 
+    pub fn update_extra_info(&mut self, mapping_item: &MappingItem) {
+        self.alchable.clone_from(&mapping_item.alchable);
+        self.limit = mapping_item.limit;
+        self.members = mapping_item.members;
+    }
+
     pub fn invalid_data(&self) -> bool {
         // Ignore special items
         let reserved = SPECIAL_ITEM_NAMES
@@ -74,6 +84,67 @@ impl Item {
         }
     }
 
+    /// Price of a single item when `buying` (or selling) with `strategy`.
+    /// Prices between high and low round against the trader. See [`crate::gp`]
+    pub fn strategy_price(&self, buying: bool, strategy: PricingStrategy) -> Option<Gp> {
+        let rounding = if buying { Rounding::Up } else { Rounding::Down };
+        let (high, low) = (self.item_prices.high, self.item_prices.low);
+
+        let midpoint = || Some(Gp::from_f64(f64::midpoint(f64::from(high?), f64::from(low?)), rounding));
+
+        match strategy {
+            PricingStrategy::Instant => self.price(buying).map(Gp::from),
+            PricingStrategy::Patient => self.price(!buying).map(Gp::from),
+            PricingStrategy::Midpoint => midpoint(),
+            PricingStrategy::VolumeWeighted => {
+                let high_volume = f64::from(self.volume(true).unwrap_or(0));
+                let low_volume = f64::from(self.volume(false).unwrap_or(0));
+                let total_volume = high_volume + low_volume;
+
+                // No volumes from the `latest` timespan
+                if total_volume <= 0.0 {
+                    return midpoint();
+                }
+
+                let weighted = f64::from(high?) * high_volume + f64::from(low?) * low_volume;
+                Some(Gp::from_f64(weighted / total_volume, rounding))
+            }
+        }
+    }
+
+    /// Number of items traded on the given side over the averaged timespan.
+    /// `None` when prices came from the `latest` timespan.
+    pub fn volume(&self, high_price: bool) -> Option<i32> {
+        if high_price {
+            self.item_prices.high_volume
+        } else {
+            self.item_prices.low_volume
+        }
+    }
+
+    /// Seconds between the last trade on the given side and `now`.
+    /// `None` for special items (e.g. `Coins`) or when the trade time is unknown.
+    pub fn price_age(&self, high_price: bool, now: i64) -> Option<i64> {
+        if SPECIAL_ITEM_NAMES.contains(&self.name.as_str()) {
+            return None;
+        }
+
+        let trade_time = if high_price {
+            self.item_prices.high_time
+        } else {
+            self.item_prices.low_time
+        }?;
+
+        Some(now.saturating_sub(i64::from(trade_time)).max(0))
+    }
+
+    pub fn total_volume(&self) -> Option<i32> {
+        match (self.item_prices.high_volume, self.item_prices.low_volume) {
+            (None, None) => None,
+            (high, low) => Some(high.unwrap_or(0).saturating_add(low.unwrap_or(0))),
+        }
+    }
+
     pub fn price_tuple(&self) -> HashMap<String, Option<i32>> {
         HashMap::from_iter([
             ("high".to_owned(), self.item_prices.high),
@@ -82,6 +153,26 @@ impl Item {
     }
 }
 
+/// Number of an item traded per hour on each side
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HourlyVolume {
+    /// Bought instantly, at the high price
+    pub high: f64,
+    /// Sold instantly, at the low price
+    pub low: f64,
+}
+
+impl HourlyVolume {
+    pub fn total(&self) -> f64 {
+        self.high + self.low
+    }
+
+    /// Volume of the side trading less
+    pub fn thinnest(&self) -> f64 {
+        self.high.min(self.low)
+    }
+}
+
 pub struct ItemSearch {
     // NOTE: **Handlers replaced by filenames**
     // pub price_data_fio: FileIO,
@@ -95,6 +186,21 @@ pub struct ItemSearch {
     // Populated from filepaths
     pub name_to_id: HashMap<String, String>,
     pub id_to_name: HashMap<String, String>,
+    // Populated by `update_item_extra_info`
+    pub mapping: HashMap<String, MappingItem>,
+
+    pub history: HistoryStore,
+    // Defaults to the `price_data` file for prices, and the api for timeseries
+    price_source: Box<dyn PriceSource>,
+    timeseries_source: Box<dyn PriceSource>,
+    ignored_items: HashSet<String>,
+    // Most recent trade across all items. Updated by `update_item_prices`
+    // Price ages are measured from here, so cached or historic prices are not all stale
+    pub latest_trade_time: Option<i64>,
+    // Item name -> number traded per hour on each side. Kept when prices change
+    pub hourly_volumes: HashMap<String, HourlyVolume>,
+    // Item name -> price used instead of the loaded one. See `override_prices`
+    pub price_overrides: HashMap<String, PriceOverride>,
 }
 
 impl ItemSearch {
@@ -103,6 +209,10 @@ impl ItemSearch {
         filepaths: FilePaths,
         api_config: crate::config::Api,
     ) -> Self {
+        let history = HistoryStore::new(filepaths.price_history.clone(), api_config.history);
+        let price_source = Box::new(FileSource::new(filepaths.price_data.clone()));
+        let timeseries_source = Box::new(HttpSource::new(&api_config));
+
         // TODO: Populate name_to_id and id_to_name
         let mut intermediate = Self {
             items, // Using Item Name(String)=>Item(Object)
@@ -110,6 +220,14 @@ impl ItemSearch {
             api_config,
             name_to_id: HashMap::new(),
             id_to_name: HashMap::new(),
+            mapping: HashMap::new(),
+            history,
+            price_source,
+            timeseries_source,
+            ignored_items: HashSet::new(),
+            latest_trade_time: None,
+            hourly_volumes: HashMap::new(),
+            price_overrides: HashMap::new(),
         };
 
         intermediate.populate_lookups();
@@ -117,6 +235,55 @@ impl ItemSearch {
         intermediate
     }
 
+    /// Prices to apply with [`ItemSearch::override_prices`]. Unknown items are skipped
+    pub fn set_price_overrides(&mut self, overrides: HashMap<String, PriceOverride>) {
+        self.price_overrides = overrides.into_iter()
+            .filter(|(name, _)| {
+                let known = self.name_to_id.contains_key(name);
+                if !known {
+                    warn!(desc = "Unknown item in price overrides", item = %name);
+                }
+                known
+            })
+            .collect();
+    }
+
+    pub fn is_overridden(&self, item_name: &str) -> bool {
+        self.price_overrides.contains_key(item_name)
+    }
+
+    /// Replace the high and low price of overridden items in `item_prices`,
+    /// before they are passed to [`ItemSearch::update_item_prices`].
+    /// Items without a price are added, as last traded with the latest trade in `item_prices`
+    pub fn override_prices(&self, item_prices: &mut PriceDataType) {
+        let latest_time = item_prices.data.values()
+            .flat_map(|datum| [datum.high_time, datum.low_time])
+            .flatten()
+            .max();
+
+        for (name, price) in &self.price_overrides {
+            let Some(id) = self.id_from_name(name) else { continue };
+            let datum = item_prices.data.entry(id.clone()).or_insert_with(|| PriceDatum {
+                high_time: latest_time,
+                low_time: latest_time,
+                ..Default::default()
+            });
+            datum.high = Some(price.high);
+            datum.low = Some(price.low);
+        }
+        debug!(desc = "Overrode item prices", count = self.price_overrides.len());
+    }
+
+    /// Replace where [`ItemSearch::get_item_prices`] loads prices from
+    pub fn set_price_source(&mut self, source: Box<dyn PriceSource>) {
+        self.price_source = source;
+    }
+
+    /// Replace where [`ItemSearch::get_item_timeseries`] requests timeseries from
+    pub fn set_timeseries_source(&mut self, source: Box<dyn PriceSource>) {
+        self.timeseries_source = source;
+    }
+
     fn populate_lookups(&mut self) {
         // Create fileio
         let mut file = FileIO::new(
@@ -159,44 +326,118 @@ impl ItemSearch {
     }
 
 
-    /// Either from file (ideally) or from the api
+    /// From the [price source](ItemSearch::set_price_source)
     /// # Panics
-    /// Will panic when the api response is empty
-    pub fn get_item_prices(&mut self, from_file: bool) -> PriceDataType {
-        let res = if from_file {
-            log_match_panic(
-                self.find_prices_from_file(),
-                "Attempting to find prices from a stored mapping file.",
-                "Failed to find prices from file. May not exist or malformed data.",
-            )
-
-        } else {
-            let api = crate::api::Api::new(&self.api_config);
-            api.request_item_prices()
-        };
+    /// Will panic when the source fails or the prices are empty
+    pub fn get_item_prices(&mut self) -> PriceDataType {
+        let res = log_match_panic(
+            self.price_source.fetch_prices(),
+            "Loaded prices from the price source.",
+            "Failed to load prices. May not exist or malformed data.",
+        );
 
         assert!(!res.data.is_empty());
 
         res
     }
 
-    /// Attempts to load item prices
-    /// from a file defined in config
-    fn find_prices_from_file(&mut self) -> Result<PriceDataType, std::io::Error> {
-        // Get correct file name and try to load contents
-        let mut price_io = crate::file_io::FileIO::new(
-            self.filepaths.price_data.clone(),
-            FileOptions::new(true, false, false),
-        );
+    /// Request the recent price history of a single item from the
+    /// [timeseries source](ItemSearch::set_timeseries_source).
+    /// Returns `None` for unknown or special items (e.g. `Coins`),
+    /// or when the request fails
+    pub fn get_item_timeseries(&self, item_name: &String, timestep: crate::config::TimeStep)
+        -> Option<TimeseriesDataType> {
+        if SPECIAL_ITEM_NAMES.contains(&item_name.as_str()) {
+            return None;
+        }
 
-        price_io.read_serialized(file_io::SerChoice::YAML)
+        let item_id = self.id_from_name(item_name)?;
+
+        match self.timeseries_source.fetch_timeseries(item_id, timestep) {
+            Ok(timeseries) => timeseries,
+            Err(e) => {
+                warn!(desc = "Failed to request price history.", item = item_name, error = %e);
+                None
+            }
+        }
+    }
+
+    /// Load the number of each item traded per hour, from the averaged `timespan`.
+    /// Requested from the [timeseries source](ItemSearch::set_timeseries_source),
+    /// since cached prices may have no volumes.
+    /// Returns the number of items with a volume. Items without trades are left unknown
+    pub fn update_item_volumes(&mut self, timespan: TimeSpan) -> usize {
+        let Some(hours) = timespan.hours() else {
+            warn!(desc = "The latest timespan has no volumes", timespan = %timespan);
+            return 0;
+        };
+
+        let volumes = match self.timeseries_source.fetch_volumes(timespan) {
+            Ok(Some(volumes)) => volumes,
+            Ok(None) => return 0,
+            Err(e) => {
+                warn!(desc = "Failed to request traded volumes.", timespan = %timespan, error = %e);
+                return 0;
+            }
+        };
+
+        for (id, datum) in volumes.data {
+            let Some(name) = self.name_from_id(&id).cloned() else {
+                continue;
+            };
+            let volume = HourlyVolume {
+                high: f64::from(datum.high_volume.unwrap_or(0)) / hours,
+                low: f64::from(datum.low_volume.unwrap_or(0)) / hours,
+            };
+            if volume.total() > 0.0 {
+                self.hourly_volumes.insert(name, volume);
+            }
+        }
+
+        self.hourly_volumes.len()
+    }
+
+    /// Store `item_prices` in the local price history, keyed by `fetched_at` (unix time)
+    /// # Errors
+    /// See [`HistoryStore::append`]
+    pub fn record_prices(&self, fetched_at: i64, item_prices: &PriceDataType) -> Result<(), std::io::Error> {
+        self.history.append(fetched_at, item_prices)
+    }
+
+    /// Replace all item prices with the latest stored snapshot at or before `timestamp`.
+    /// Extra information and ignored items are kept.
+    /// Returns the fetch time of the snapshot used, or `None` (prices unchanged) if there is none.
+    /// # Errors
+    /// See [`HistoryStore::as_of`]
+    #[instrument(level = "debug", skip(self))]
+    pub fn set_prices_as_of(&mut self, timestamp: i64) -> Result<Option<i64>, std::io::Error> {
+        let Some((fetched_at, item_prices)) = self.history.as_of(timestamp)? else {
+            warn!(desc = "No stored prices at or before timestamp", timestamp);
+            return Ok(None);
+        };
+
+        self.set_prices(item_prices);
+
+        debug!(desc = "Resolved prices from history", fetched_at);
+        Ok(Some(fetched_at))
+    }
+
+    /// Replace all item prices with `item_prices`, e.g. a stored snapshot.
+    /// Extra information and ignored items are kept, and price overrides are applied
+    pub fn set_prices(&mut self, mut item_prices: PriceDataType) {
+        self.items.clear();
+        self.override_prices(&mut item_prices);
+        self.update_item_prices(item_prices);
     }
 
     /// Removes items from the internal list.
+    /// These items will also be skipped by later price updates.
     /// Returns number of items removed.
     #[instrument(level = "debug", skip(self))]
     pub fn ignore_items(&mut self, item_name_list: &Vec<String>) -> i32 {
         debug!(desc = "Removing ignored items...");
+        self.ignored_items.extend(item_name_list.iter().cloned());
+
         match item_name_list
             .iter()
             .filter_map(|x| self.items.remove(x))
@@ -222,6 +463,8 @@ impl ItemSearch {
             // Start of time
             high_time: Some(START_TIME),
             low_time: Some(START_TIME),
+            high_volume: None,
+            low_volume: None,
         };
 
         // let coins_datum = self.item_from_id_price("Coins".to_string(), coins_prices)
@@ -239,10 +482,10 @@ impl ItemSearch {
 
     #[instrument(level = "trace", skip(self, item_prices))]
     /// Update existing item price list with new entries
-    /// Calls `HashMap::extend`
-    /// TODO(2): Currently this is only called once at the start of the program
-    ///     However it would duplicate items if called later on,
-    ///     from the self.items.insert(...) call
+    /// Existing items keep their extra information, only the prices are replaced.
+    /// New items take their extra information from the mapping (if loaded).
+    /// Entries with a price on only one side are kept, for both `latest` and averaged timespans.
+    /// Recipes needing the missing side are excluded when priced. See [`crate::prices::prices::PriceHandle::parse_item_list`]
     pub fn update_item_prices(&mut self, item_prices: PriceDataType) {
         // TODO(1): Impl Iterator or some trait so don't have to call data field
         // self.items.extend(item_prices.data)
@@ -256,18 +499,40 @@ impl ItemSearch {
 
             let price_data = iprice.1;
 
-            // TODO(2): Currently this provides a default value since the items
-            //  don't exist before this method is called
-            let item = Item::new(name.clone(), id, price_data,
+            if self.ignored_items.contains(&name) {
+                continue;
+            }
+
+            if let Some(item) = self.items.get_mut(&name) {
+                item.item_prices = price_data;
+                continue;
+            }
+
+            // Default values until the mapping is loaded
+            let mut item = Item::new(name.clone(), id, price_data,
                 None, // Alchable
                 None, // Limit
                 true, // Members
             );
+            if let Some(mapping_item) = self.mapping.get(&name) {
+                item.update_extra_info(mapping_item);
+            }
 
             self.items.insert(name, item);
         }
-
         self.add_special_price_values();
+        self.update_latest_trade_time();
+    }
+
+    fn update_latest_trade_time(&mut self) {
+        self.latest_trade_time = self.items
+            .values()
+            .filter(|item| !SPECIAL_ITEM_NAMES.contains(&item.name.as_str()))
+            .flat_map(|item| [item.item_prices.high_time, item.item_prices.low_time])
+            .flatten()
+            .max()
+            .map(i64::from);
+        debug!(desc = "Latest trade time", time = ?self.latest_trade_time);
     }
 
 
@@ -297,11 +562,11 @@ impl ItemSearch {
             };
             
             // Update item information
-            lookup_item.alchable.clone_from(&item.alchable);
-            lookup_item.limit = item.limit;
-            lookup_item.members = item.members;
-
+            lookup_item.update_extra_info(item);
         }
+
+        // Kept for items added by later price updates
+        self.mapping = mapping_items;
         Ok(())
     }
 }
@@ -1,4 +1,8 @@
 pub mod data_types;
+pub mod history;
 #[allow(clippy::module_inception)]
 pub mod item_search;
+pub mod price_source;
+pub mod recipe_graph;
 pub mod recipes;
+pub mod validation;
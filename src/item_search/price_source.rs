@@ -0,0 +1,186 @@
+//! Where item prices and single item timeseries are loaded from.
+//! Injected into [`ItemSearch`](super::item_search::ItemSearch),
+//! so the whole pipeline can run offline against stored or in-memory prices.
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use tracing::trace;
+
+use crate::api::{Api, ApiError};
+use crate::config::{TimeSpan, TimeStep};
+use crate::file_io::{FileIO, FileOptions, SerChoice};
+
+use super::data_types::{latest::PriceDataType, timeseries::TimeseriesDataType};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PriceSourceError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error("Failed to read prices: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub trait PriceSource {
+    /// Current prices of every item, keyed by item id
+    /// # Errors
+    /// See [`PriceSourceError`]
+    fn fetch_prices(&self) -> Result<PriceDataType, PriceSourceError>;
+
+    /// Recent averaged prices of a single item.
+    /// `None` when the source has no timeseries for the item.
+    /// # Errors
+    /// See [`PriceSourceError`]
+    fn fetch_timeseries(&self, item_id: &str, timestep: TimeStep)
+        -> Result<Option<TimeseriesDataType>, PriceSourceError>;
+
+    /// Averaged prices with trade volumes over `timespan`, keyed by item id.
+    /// `None` when the source has no volumes.
+    /// # Errors
+    /// See [`PriceSourceError`]
+    fn fetch_volumes(&self, timespan: TimeSpan) -> Result<Option<PriceDataType>, PriceSourceError>;
+}
+
+/// Live prices from the wiki API. See [`Api`]
+pub struct HttpSource {
+    api: Api,
+}
+
+impl HttpSource {
+    pub fn new(api_config: &crate::config::Api) -> Self {
+        Self { api: Api::new(api_config) }
+    }
+}
+
+impl PriceSource for HttpSource {
+    fn fetch_prices(&self) -> Result<PriceDataType, PriceSourceError> {
+        Ok(self.api.request_item_prices()?)
+    }
+
+    fn fetch_timeseries(&self, item_id: &str, timestep: TimeStep)
+        -> Result<Option<TimeseriesDataType>, PriceSourceError> {
+        Ok(Some(self.api.request_timeseries(item_id, timestep)?))
+    }
+
+    fn fetch_volumes(&self, timespan: TimeSpan) -> Result<Option<PriceDataType>, PriceSourceError> {
+        Ok(Some(self.api.request_timespan_prices(timespan.into())?))
+    }
+}
+
+/// Prices stored in a single file, e.g. `filepaths.price_data`.
+/// Has no timeseries or volumes.
+pub struct FileSource {
+    path: String,
+}
+
+impl FileSource {
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// Read a serialized price or timeseries file
+fn read_file<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    trace!(desc = "Reading price file", path = %path.display());
+    let mut price_io = FileIO::new(
+        path.to_string_lossy().into_owned(),
+        FileOptions::new(true, false, false),
+    );
+
+    price_io.read_serialized(SerChoice::YAML)
+}
+
+impl PriceSource for FileSource {
+    fn fetch_prices(&self) -> Result<PriceDataType, PriceSourceError> {
+        Ok(read_file(Path::new(&self.path))?)
+    }
+
+    fn fetch_timeseries(&self, _item_id: &str, _timestep: TimeStep)
+        -> Result<Option<TimeseriesDataType>, PriceSourceError> {
+        Ok(None)
+    }
+
+    fn fetch_volumes(&self, _timespan: TimeSpan) -> Result<Option<PriceDataType>, PriceSourceError> {
+        Ok(None)
+    }
+}
+
+/// A directory of recorded API responses:
+/// `prices.json`, and optionally `timeseries/<item id>_<timestep>.json`
+/// and `volumes_<timespan>.json`
+pub struct FixtureSource {
+    directory: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self { directory: directory.into() }
+    }
+}
+
+impl PriceSource for FixtureSource {
+    fn fetch_prices(&self) -> Result<PriceDataType, PriceSourceError> {
+        Ok(read_file(&self.directory.join("prices.json"))?)
+    }
+
+    fn fetch_timeseries(&self, item_id: &str, timestep: TimeStep)
+        -> Result<Option<TimeseriesDataType>, PriceSourceError> {
+        let path = self.directory
+            .join("timeseries")
+            .join(format!("{item_id}_{timestep}.json"));
+
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(read_file(&path)?))
+    }
+
+    fn fetch_volumes(&self, timespan: TimeSpan) -> Result<Option<PriceDataType>, PriceSourceError> {
+        let path = self.directory.join(format!("volumes_{timespan}.json"));
+
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(read_file(&path)?))
+    }
+}
+
+/// Prices built in code
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySource {
+    pub prices: PriceDataType,
+    // (Item id, timestep) -> timeseries
+    pub timeseries: HashMap<(String, TimeStep), TimeseriesDataType>,
+    pub volumes: HashMap<TimeSpan, PriceDataType>,
+}
+
+impl InMemorySource {
+    pub fn new(prices: PriceDataType) -> Self {
+        Self { prices, timeseries: HashMap::new(), volumes: HashMap::new() }
+    }
+
+    pub fn add_timeseries<S: Into<String>>(&mut self, item_id: S, timestep: TimeStep, timeseries: TimeseriesDataType) {
+        self.timeseries.insert((item_id.into(), timestep), timeseries);
+    }
+
+    pub fn add_volumes(&mut self, timespan: TimeSpan, volumes: PriceDataType) {
+        self.volumes.insert(timespan, volumes);
+    }
+}
+
+impl PriceSource for InMemorySource {
+    fn fetch_prices(&self) -> Result<PriceDataType, PriceSourceError> {
+        Ok(self.prices.clone())
+    }
+
+    fn fetch_timeseries(&self, item_id: &str, timestep: TimeStep)
+        -> Result<Option<TimeseriesDataType>, PriceSourceError> {
+        Ok(self.timeseries.get(&(item_id.to_string(), timestep)).cloned())
+    }
+
+    fn fetch_volumes(&self, timespan: TimeSpan) -> Result<Option<PriceDataType>, PriceSourceError> {
+        Ok(self.volumes.get(&timespan).cloned())
+    }
+}
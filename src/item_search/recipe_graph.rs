@@ -0,0 +1,92 @@
+//! Links between recipes, through the items one recipe produces and another consumes
+use std::collections::HashMap;
+
+use super::{
+    data_types::latest::SPECIAL_ITEM_NAMES,
+    recipes::{Recipe, RecipeBook},
+};
+
+/// Which recipes produce each item.
+/// Only recipes with a single output item count as producers,
+/// so methods like monster kills are not a source of every item they drop
+#[derive(Debug, Default, Clone)]
+pub struct RecipeGraph {
+    /// Item name -> (recipe name, quantity made per recipe), ordered by recipe name
+    producers: HashMap<String, Vec<(String, f32)>>,
+}
+
+impl RecipeGraph {
+    pub fn new(recipe_book: &RecipeBook) -> Self {
+        let mut producers: HashMap<String, Vec<(String, f32)>> = HashMap::new();
+
+        for recipe in recipe_book.recipes.values() {
+            let outputs = recipe.expected_outputs();
+            let mut outputs = outputs.iter();
+            let (Some((item_name, &quantity)), None) = (outputs.next(), outputs.next()) else {
+                continue;
+            };
+            if SPECIAL_ITEM_NAMES.contains(&item_name.as_str()) || quantity <= 0.0 {
+                continue;
+            }
+
+            producers.entry(item_name.clone())
+                .or_default()
+                .push((recipe.name.clone(), quantity));
+        }
+
+        for recipes in producers.values_mut() {
+            recipes.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        Self { producers }
+    }
+
+    /// Recipes producing `item_name`, with the quantity each makes
+    pub fn producers(&self, item_name: &str) -> &[(String, f32)] {
+        self.producers.get(item_name).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_intermediate(&self, item_name: &str) -> bool {
+        !self.producers(item_name).is_empty()
+    }
+
+    /// Inputs of `recipe` that another recipe can produce, ordered by name
+    pub fn intermediates<'a>(&self, recipe: &'a Recipe) -> Vec<&'a String> {
+        let mut items: Vec<&String> = recipe.inputs.inputs.keys()
+            .filter(|item_name| self.producers(item_name).iter().any(|(producer, _)| *producer != recipe.name))
+            .collect();
+        items.sort();
+        items
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recipe(name: &str, inputs: &[(&str, f32)], outputs: &[(&str, f32)]) -> Recipe {
+        let mut recipe = Recipe { name: name.to_string(), ..Recipe::default() };
+        recipe.inputs.inputs = inputs.iter().map(|(item, quantity)| ((*item).to_string(), *quantity)).collect();
+        recipe.outputs = outputs.iter().map(|(item, quantity)| ((*item).to_string(), *quantity)).collect();
+        recipe
+    }
+
+    #[test]
+    fn single_output_recipes_produce() {
+        let mut recipe_book = RecipeBook::default();
+        recipe_book.add_from_list(vec![
+            recipe("Smelting steel bars", &[("Iron ore", 9.0), ("Coal", 18.0)], &[("Steel bar", 9.0)]),
+            recipe("Smithing Steel cannonballs", &[("Steel bar", 2.0)], &[("Steel cannonball", 8.0)]),
+            recipe("Killing Gargoyles", &[("Coins", 15_300.0)], &[("Steel bar", 105.0), ("Coins", 5_000.0)]),
+            recipe("Selling Steel bars", &[("Steel bar", 1.0)], &[("Coins", 300.0)]),
+        ]);
+        let graph = RecipeGraph::new(&recipe_book);
+
+        assert_eq!(graph.producers("Steel bar"), [("Smelting steel bars".to_string(), 9.0)]);
+        assert!(!graph.is_intermediate("Coins"));
+        assert!(!graph.is_intermediate("Iron ore"));
+
+        let cannonballs = &recipe_book.recipes["Smithing Steel cannonballs"];
+        assert_eq!(graph.intermediates(cannonballs), [&"Steel bar".to_string()]);
+    }
+}
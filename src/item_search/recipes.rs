@@ -1,14 +1,21 @@
 //! Handling recipes defined in `lookup_data/recipes.yaml`
 use serde::{de::Visitor, Deserialize};
+use serde_yaml_ng::Value;
 
 use crate::{
+    config::PricingStrategy,
     file_io::{FileIO, FileOptions},
+    gp::Gp,
+    item_search::item_search::ItemSearch,
     log_match_panic,
 };
 use tracing::{debug, trace, warn};
 
 use std::{collections::{HashMap, HashSet}, fmt::Debug};
 
+/// A single cast of High Level Alchemy
+pub const HIGH_ALCH_TICKS: f32 = 5.0;
+
 // #[serde(untagged)]
 #[derive(Debug, Default, Clone)]
 pub enum RecipeTime {
@@ -30,10 +37,63 @@ pub struct Recipe {
     #[serde(flatten)]
     pub inputs: RecipeInputs,
 
+    #[serde(default)]
     pub outputs: HashMap<String, f32>,
 
+    /// Outputs only received some of the time.
+    /// Priced at their expected quantity, alongside `outputs`
+    #[serde(default)]
+    pub drops: HashMap<String, RecipeDrop>,
+
     #[serde(alias = "time")]
     pub ticks: RecipeTime,
+
+    /// Overrides `profit.pricing` in the config
+    #[serde(default)]
+    pub pricing: Option<PricingStrategy>,
+}
+
+/// An output received with `chance` each time the recipe is made
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct RecipeDrop {
+    pub chance: f64,
+    #[serde(default)]
+    pub quantity: DropQuantity,
+}
+
+/// Quantity of a single drop. Either fixed or a `[min, max]` range
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum DropQuantity {
+    Fixed(u32),
+    Range(u32, u32),
+}
+
+impl Default for DropQuantity {
+    fn default() -> Self {
+        Self::Fixed(1)
+    }
+}
+
+impl DropQuantity {
+    /// Smallest and largest quantity, inclusive
+    pub fn bounds(self) -> (u32, u32) {
+        match self {
+            Self::Fixed(quantity) => (quantity, quantity),
+            Self::Range(min, max) => (min.min(max), min.max(max)),
+        }
+    }
+
+    pub fn mean(self) -> f64 {
+        let (min, max) = self.bounds();
+        f64::midpoint(f64::from(min), f64::from(max))
+    }
+}
+
+impl RecipeDrop {
+    pub fn expected_quantity(&self) -> f64 {
+        self.chance * self.quantity.mean()
+    }
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -45,6 +105,28 @@ pub struct RecipeInputs {
 }
 
 
+#[derive(Debug, thiserror::Error)]
+pub enum RecipeFileError {
+    #[error("Failed to read recipes: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Recipes are not valid YAML: {0}")]
+    Yaml(#[source] serde_yaml_ng::Error),
+    #[error("Invalid recipe `{entry}`: {source}")]
+    Entry {
+        entry: String,
+        #[source]
+        source: serde_yaml_ng::Error,
+    },
+}
+
+/// An entry of `lookup_data/recipes.yaml` with a `family` table.
+/// Each row of the table fills in the `{placeholder}`s of the rest of the entry to make one recipe
+#[derive(Debug, Clone)]
+pub struct RecipeFamily {
+    pub rows: Vec<HashMap<String, Value>>,
+    pub template: Value,
+}
+
 #[derive(Debug, Deserialize, Default)]
 pub struct RecipeBook {
     pub recipes: HashMap<String, Recipe>,
@@ -120,8 +202,35 @@ impl Recipe {
             number_per_hour: None,
             inputs,
             outputs,
+            drops: HashMap::new(),
             ticks: ticks.into(),
+            pricing: None,
+        }
+    }
+
+    /// Buy `item_name` and a Nature rune, then cast High Level Alchemy for `highalch` coins.
+    /// Fire runes are assumed to come from a staff
+    pub fn high_alchemy(item_name: &str, highalch: i32, members: bool) -> Self {
+        let inputs = RecipeInputs {
+            pay_once: None,
+            inputs: HashMap::from([(item_name.to_string(), 1.0), ("Nature rune".to_string(), 1.0)]),
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let outputs = HashMap::from([("Coins".to_string(), highalch as f32)]);
+
+        let mut recipe = Self::new(format!("High alching {item_name}"), inputs, outputs, HIGH_ALCH_TICKS);
+        recipe.members = members;
+        recipe
+    }
+
+    /// `outputs` with the expected quantity of each drop added
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn expected_outputs(&self) -> HashMap<String, f32> {
+        let mut outputs = self.outputs.clone();
+        for (name, drop) in &self.drops {
+            *outputs.entry(name.clone()).or_default() += drop.expected_quantity() as f32;
         }
+        outputs
     }
 
     pub fn isvalid(&self) -> bool {
@@ -129,6 +238,82 @@ impl Recipe {
     }
 }
 
+impl RecipeFamily {
+    /// # Errors
+    /// When a filled in template is not a valid [`Recipe`]
+    pub fn expand(&self) -> Result<Vec<Recipe>, serde_yaml_ng::Error> {
+        self.rows.iter()
+            .map(|row| {
+                let recipe: Recipe = serde_yaml_ng::from_value(fill_placeholders(self.template.clone(), row))?;
+                if recipe.name.contains('{') {
+                    warn!(desc = "Unfilled placeholder in recipe family", name = %recipe.name);
+                }
+                Ok(recipe)
+            })
+            .collect()
+    }
+}
+
+/// Recipes of one entry in the recipes file: a single recipe, or every recipe of a [`RecipeFamily`]
+/// # Errors
+/// When the entry, or a recipe of its family, is not a valid [`Recipe`]
+pub fn parse_recipe_entry(mut entry: Value) -> Result<Vec<Recipe>, serde_yaml_ng::Error> {
+    let family = entry.as_mapping_mut().and_then(|mapping| mapping.remove("family"));
+    match family {
+        None => Ok(vec![serde_yaml_ng::from_value(entry)?]),
+        Some(rows) => RecipeFamily { rows: serde_yaml_ng::from_value(rows)?, template: entry }.expand(),
+    }
+}
+
+/// Recipes in the file at `recipe_path` with the key of the entry each came from, in file order.
+/// Families are expanded. See [`parse_recipe_entry`]
+/// # Errors
+/// See [`RecipeFileError`]
+pub fn read_recipe_entries(recipe_path: String) -> Result<Vec<(String, Recipe)>, RecipeFileError> {
+    let mut recipes_fio = FileIO::new(recipe_path, FileOptions::new(true, false, false));
+    let entries: serde_yaml_ng::Mapping = serde_yaml_ng::from_reader(recipes_fio.get_reader()?)
+        .map_err(RecipeFileError::Yaml)?;
+
+    let mut recipes = Vec::new();
+    for (key, entry) in entries {
+        let key = key.as_str().map_or_else(|| format!("{key:?}"), str::to_string);
+        let family = parse_recipe_entry(entry)
+            .map_err(|source| RecipeFileError::Entry { entry: key.clone(), source })?;
+        recipes.extend(family.into_iter().map(|recipe| (key.clone(), recipe)));
+    }
+
+    Ok(recipes)
+}
+
+/// Replace each `{key}` in the strings of `value` with its value in `row`.
+/// A string that is only a placeholder takes the row's value as is, so quantities stay numbers
+fn fill_placeholders(value: Value, row: &HashMap<String, Value>) -> Value {
+    match value {
+        Value::String(text) => {
+            if let Some(key) = text.strip_prefix('{').and_then(|text| text.strip_suffix('}'))
+                && let Some(value) = row.get(key) {
+                return value.clone();
+            }
+            Value::String(row.iter().fold(text, |text, (key, value)| {
+                let filled = match value {
+                    Value::String(filled) => filled.clone(),
+                    Value::Number(number) => number.to_string(),
+                    Value::Bool(boolean) => boolean.to_string(),
+                    _ => return text,
+                };
+                text.replace(&format!("{{{key}}}"), &filled)
+            }))
+        },
+        Value::Mapping(mapping) => Value::Mapping(mapping.into_iter()
+            .map(|(key, value)| (fill_placeholders(key, row), fill_placeholders(value, row)))
+            .collect()),
+        Value::Sequence(values) => Value::Sequence(values.into_iter()
+            .map(|value| fill_placeholders(value, row))
+            .collect()),
+        other => other,
+    }
+}
+
 impl RecipeBook {
     pub fn new<H: Into<HashMap<String, Recipe>>>(recipes: H) -> Self {
         Self {
@@ -137,19 +322,14 @@ impl RecipeBook {
     }
 
     pub fn load_default_recipes(&mut self, recipe_path: String) {
-        // let recipes_fio = Logging::<FileIO<S>>::new(, recipe_path);
-        let mut recipes_fio = FileIO::new(recipe_path, FileOptions::new(true, false, false));
-
-        // TODO: Implement choice of other SerChoice options
-        let file_output =
-            recipes_fio.read_serialized::<HashMap<String, Recipe>>(crate::file_io::SerChoice::YAML);
-
+        // Families expand into one recipe per row
         let mut recipe_list: Vec<Recipe> = log_match_panic(
-            file_output,
+            read_recipe_entries(recipe_path),
             "Read recipe list from file.",
             "Failed to load recipes.",
         )
-        .into_values()
+        .into_iter()
+        .map(|(_, recipe)| recipe)
         .collect();
 
         // Filer out invalid recipes; using .isvalid()
@@ -237,6 +417,35 @@ impl RecipeBook {
         }
     }
 
+    /// Add a [`Recipe::high_alchemy`] for every alchable item, keeping any recipe of the same name.
+    /// Items that lose coins at their current `pricing` are skipped, as there are thousands of alchable items.
+    /// Items without a buy limit are also skipped.
+    /// Returns the number added
+    pub fn add_high_alchemy_recipes(&mut self, items: &ItemSearch, pricing: PricingStrategy) -> usize {
+        let Some(nature_rune) = items.item_by_name(&"Nature rune".to_string())
+            .and_then(|item| item.strategy_price(true, pricing)) else {
+            warn!(desc = "No price for Nature rune. Skipping high alchemy recipes.");
+            return 0;
+        };
+
+        let recipes: Vec<Recipe> = items.items.values()
+            .filter_map(|item| {
+                // Without a known buy limit, nothing caps the number of casts
+                item.limit?;
+                let highalch = item.alchable.as_ref()?.highalch;
+                let cost = item.strategy_price(true, pricing)? + nature_rune;
+                (Gp::from(highalch) > cost).then(|| Recipe::high_alchemy(&item.name, highalch, item.members))
+            })
+            .filter(|recipe| !self.recipes.contains_key(&recipe.name))
+            .collect();
+
+        let added = recipes.len();
+        self.add_from_list(recipes);
+        debug!(desc = "Added high alchemy recipes", count = added);
+
+        added
+    }
+
     pub fn get_all_recipes(&self) -> HashMap<String, Recipe> {
         self.recipes.clone()
     }
@@ -282,3 +491,167 @@ impl<F: Into<f32>> From<F> for RecipeTime {
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn family_fills_placeholders() {
+        let entry: Value = serde_yaml_ng::from_str(r#"
+            family:
+              - { metal: Steel, ore: Iron ore, ores: 9 }
+              - { metal: Mithril, ore: Mithril ore, ores: 5 }
+            name: "Smelting {metal} Bars"
+            members: false
+            inputs:
+              "{ore}": "{ores}"
+              Coal: 18
+            outputs:
+              "{metal} bar": "{ores}"
+            time: 36
+        "#).unwrap();
+
+        let recipes = parse_recipe_entry(entry).unwrap();
+        let mithril = &recipes[1];
+        assert_eq!(mithril.name, "Smelting Mithril Bars");
+        assert_eq!(mithril.inputs.inputs, HashMap::from([("Mithril ore".to_string(), 5.0), ("Coal".to_string(), 18.0)]));
+        assert_eq!(mithril.outputs, HashMap::from([("Mithril bar".to_string(), 5.0)]));
+        assert_eq!(recipes[0].outputs["Steel bar"], 9.0);
+    }
+
+    #[test]
+    fn placeholders_fill_keys_and_text() {
+        let row = HashMap::from([
+            ("metal".to_string(), Value::from("Mithril")),
+            ("ores".to_string(), Value::from(5)),
+        ]);
+        let template: Value = serde_yaml_ng::from_str(r#"
+            "{metal} bar": "{ores}"
+            note: "{ores} {metal} ores"
+            times: ["{ores}", 2]
+        "#).unwrap();
+        let expected: Value = serde_yaml_ng::from_str(r#"
+            "Mithril bar": 5
+            note: "5 Mithril ores"
+            times: [5, 2]
+        "#).unwrap();
+
+        assert_eq!(fill_placeholders(template, &row), expected);
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left() {
+        let row = HashMap::from([("metal".to_string(), Value::from("Steel"))]);
+        assert_eq!(fill_placeholders(Value::from("{metal} {colour} bar"), &row), Value::from("Steel {colour} bar"));
+
+        // A row missing a quantity leaves a string where a number is needed
+        let entry: Value = serde_yaml_ng::from_str(r#"
+            family:
+              - { metal: Steel, bars: 9 }
+              - { metal: Iron }
+            name: "Smelting {metal} bars"
+            members: false
+            inputs:
+              Coal: 1
+            outputs:
+              "{metal} bar": "{bars}"
+            time: 4
+        "#).unwrap();
+        assert!(parse_recipe_entry(entry).is_err());
+    }
+
+    #[test]
+    fn default_recipes_keep_their_names() {
+        let mut names: Vec<String> = read_recipe_entries("lookup_data/recipes.yaml".to_string())
+            .unwrap()
+            .into_iter()
+            .filter(|(_, recipe)| recipe.isvalid())
+            .map(|(_, recipe)| recipe.name)
+            .collect();
+        names.sort();
+
+        // Every recipe the file had before families were added
+        assert_eq!(names, [
+            "Casting Tan Leather (Black d'hide)",
+            "Casting Tan Leather (Red d'hide)",
+            "Catching tuna & swordfish (F2P)",
+            "Charging and alchemising bracelets of ethereum",
+            "Cooking anchovies",
+            "Cooking karambwan",
+            "Crushing bird nests (Auto)",
+            "Crushing bird nests (Manual)",
+            "Cutting yew logs",
+            "Degriming grimy avantoe",
+            "Degriming grimy cadantine",
+            "Degriming grimy lantadyme",
+            "Degriming grimy ranarr weed",
+            "Degriming grimy torstol",
+            "Diamond Amulets",
+            "Diamond Necklaces",
+            "Diamond Rings",
+            "Dismantling bracelets of ethereum",
+            "Emerald Amulets",
+            "Emerald Necklaces",
+            "Emerald Rings",
+            "Enchanting dragonstone jewellery (Autocast)",
+            "Enchanting dragonstone jewellery (Manual)",
+            "Gargoyles (Off-task)",
+            "Grinding chocolate bars (auto)",
+            "Grinding chocolate bars (manual)",
+            "Humidify Clay",
+            "Killing Urium Shades",
+            "Making Prayer potion(3)",
+            "Making Prayer potion(4)",
+            "Making Prayer regeneration potion(3)",
+            "Making Prayer regeneration potion(4)",
+            "Making Super energy potion(3)",
+            "Making Super energy potions",
+            "Making ardougne teleport tablets",
+            "Making avantoe potions",
+            "Making cadantine potions",
+            "Making dwarf weed potions",
+            "Making guam potions",
+            "Making irit potions",
+            "Making kwuarm potions",
+            "Making lantadyme potions",
+            "Making mahogany planks",
+            "Making oak planks",
+            "Making planks",
+            "Making ranarr potions",
+            "Making snapdragon potions",
+            "Making teak planks",
+            "Making teleport to house tablets",
+            "Making toadflax potions",
+            "Making varrock teleport tablets",
+            "Poisoning amethyst arrow",
+            "Poisoning amethyst arrow(+)",
+            "Poisoning amethyst arrow(++)",
+            "Poisoning dragon arrow",
+            "Poisoning dragon arrow(+)",
+            "Poisoning dragon arrow(++)",
+            "Poisoning dragon dart",
+            "Poisoning dragon dart(+)",
+            "Poisoning dragon dart(++)",
+            "Ruby Amulets",
+            "Ruby Necklaces",
+            "Ruby Necklaces then HA",
+            "Ruby Rings",
+            "Sapphire Amulets",
+            "Sapphire Necklaces",
+            "Sapphire Rings",
+            "Smelting Adamantite Bars",
+            "Smelting Iron Bars",
+            "Smelting Mithril Bars",
+            "Smelting Steel Bars",
+            "Smithing Adamantite cannonballs",
+            "Smithing Bronze cannonballs",
+            "Smithing Iron cannonballs",
+            "Smithing Mithril cannonballs",
+            "Smithing Rune cannonballs",
+            "Smithing Steel cannonballs",
+            "Stealing valuables",
+            "Stranglewood fishing",
+        ]);
+    }
+}
@@ -0,0 +1,190 @@
+//! Checks of every recipe in `lookup_data/recipes.yaml`, for the `validate` command.
+//! Catches what would otherwise only drop a recipe at runtime
+use std::{collections::HashMap, fmt, hash::BuildHasher};
+
+use super::{data_types::latest::SPECIAL_ITEM_NAMES, recipes::Recipe};
+
+/// Most close matches suggested for an unknown item
+const MAX_SUGGESTIONS: usize = 3;
+/// Least Jaro-Winkler similarity of a suggestion, ignoring case
+const MIN_SIMILARITY: f64 = 0.85;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Any is enough for `validate` to fail
+    Error,
+    /// Reported, but the recipe file is still valid. e.g. a recipe skipped at runtime
+    Warning,
+}
+
+/// A problem with one recipe
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// Key of the entry in the recipes file
+    pub entry: String,
+    /// Display name of the recipe
+    pub recipe: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.entry == self.recipe {
+            write!(f, "`{}`: {}", self.recipe, self.message)
+        } else {
+            write!(f, "`{}` (in `{}`): {}", self.recipe, self.entry, self.message)
+        }
+    }
+}
+
+/// Every problem with `recipes`, as read by [`super::recipes::read_recipe_entries`].
+/// Item names are checked against `name_to_id`, along with [`SPECIAL_ITEM_NAMES`] like Coins
+pub fn validate_recipes<S: BuildHasher>(recipes: &[(String, Recipe)], name_to_id: &HashMap<String, String, S>) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    // Display name -> entries using it
+    let mut entries_by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (entry, recipe) in recipes {
+        entries_by_name.entry(&recipe.name).or_default().push(entry);
+    }
+
+    for (entry, recipe) in recipes {
+        // Skipped when loaded, so the file still works
+        if !recipe.isvalid() {
+            issues.push(Issue {
+                entry: entry.clone(),
+                recipe: recipe.name.clone(),
+                severity: Severity::Warning,
+                message: "Neither `time` nor `number_per_hour` is set, so the recipe is skipped".to_string(),
+            });
+        }
+
+        let mut issue = |message| issues.push(Issue {
+            entry: entry.clone(),
+            recipe: recipe.name.clone(),
+            severity: Severity::Error,
+            message,
+        });
+
+        if entries_by_name[recipe.name.as_str()].len() > 1 {
+            let entries: Vec<String> = entries_by_name[recipe.name.as_str()].iter().map(|entry| format!("`{entry}`")).collect();
+            issue(format!("Display name is used by {}", entries.join(", ")));
+        }
+
+        if recipe.outputs.is_empty() && recipe.drops.is_empty() {
+            issue("No `outputs` or `drops`".to_string());
+        }
+
+        let quantities = [
+            ("pay_once", recipe.inputs.pay_once.as_ref()),
+            ("inputs", Some(&recipe.inputs.inputs)),
+            ("outputs", Some(&recipe.outputs)),
+        ];
+        for (field, items) in quantities {
+            for (item_name, quantity) in sorted(items.into_iter().flatten()) {
+                if quantity.is_nan() || *quantity <= 0.0 {
+                    issue(format!("Quantity of `{item_name}` in `{field}` is {quantity}"));
+                }
+            }
+        }
+
+        for (item_name, drop) in sorted(&recipe.drops) {
+            if !(drop.chance > 0.0 && drop.chance <= 1.0) {
+                issue(format!("Chance of `{item_name}` in `drops` is {}", drop.chance));
+            }
+            if drop.quantity.bounds().1 == 0 {
+                issue(format!("Quantity of `{item_name}` in `drops` is 0"));
+            }
+        }
+
+        // Handed straight back, so most likely a copy-paste error
+        for (item_name, output) in sorted(&recipe.expected_outputs()) {
+            if recipe.inputs.inputs.get(item_name) == Some(output) {
+                issue(format!("`{item_name}` is output exactly as much as it is input ({output})"));
+            }
+        }
+
+        let mut item_names: Vec<&String> = recipe.inputs.pay_once.iter().flatten()
+            .chain(&recipe.inputs.inputs)
+            .chain(&recipe.outputs)
+            .map(|(item_name, _)| item_name)
+            .chain(recipe.drops.keys())
+            .collect();
+        item_names.sort();
+        item_names.dedup();
+
+        let is_known = |item_name: &str| name_to_id.contains_key(item_name) || SPECIAL_ITEM_NAMES.contains(&item_name);
+        for item_name in item_names.into_iter().filter(|item_name| !is_known(item_name)) {
+            let suggestions: Vec<String> = suggestions(item_name, name_to_id.keys())
+                .into_iter()
+                .map(|suggestion| format!("`{suggestion}`"))
+                .collect();
+            let message = if suggestions.is_empty() {
+                format!("Unknown item `{item_name}`")
+            } else {
+                format!("Unknown item `{item_name}`. Did you mean {}?", suggestions.join(", "))
+            };
+            issue(message);
+        }
+    }
+
+    issues
+}
+
+/// Known names closest to `name`, best first
+pub fn suggestions<'a>(name: &str, known: impl IntoIterator<Item = &'a String>) -> Vec<&'a str> {
+    let name = name.to_lowercase();
+    let mut scored: Vec<(f64, &str)> = known.into_iter()
+        .map(|known| (strsim::jaro_winkler(&name, &known.to_lowercase()), known.as_str()))
+        .filter(|(similarity, _)| *similarity >= MIN_SIMILARITY)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+    scored.into_iter().take(MAX_SUGGESTIONS).map(|(_, known)| known).collect()
+}
+
+/// Items of a recipe in name order, so issues are reported the same way each run
+fn sorted<'a, V>(items: impl IntoIterator<Item = (&'a String, &'a V)>) -> Vec<(&'a String, &'a V)> {
+    let mut items: Vec<_> = items.into_iter().collect();
+    items.sort_by(|a, b| a.0.cmp(b.0));
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flags_unknown_items_and_duplicates() {
+        let name_to_id = HashMap::from([
+            ("Gold bar".to_string(), "2357".to_string()),
+            ("Ruby".to_string(), "1603".to_string()),
+            ("Ruby ring".to_string(), "1641".to_string()),
+        ]);
+
+        let mut ring = Recipe { name: "Ruby Rings".to_string(), ticks: 3.0_f32.into(), ..Recipe::default() };
+        ring.inputs.inputs = HashMap::from([("Gold bar".to_string(), 1.0), ("Rubby".to_string(), 1.0), ("Coins".to_string(), 350.0)]);
+        ring.outputs = HashMap::from([("Ruby ring".to_string(), 1.0)]);
+        let mut copy = ring.clone();
+        copy.inputs.inputs = HashMap::from([("Ruby ring".to_string(), 1.0)]);
+
+        let recipes = vec![("Ruby rings".to_string(), ring), ("Ruby rings again".to_string(), copy)];
+        let messages: Vec<String> = validate_recipes(&recipes, &name_to_id).iter().map(|issue| issue.message.clone()).collect();
+
+        assert_eq!(messages, vec![
+            "Display name is used by `Ruby rings`, `Ruby rings again`".to_string(),
+            "Unknown item `Rubby`. Did you mean `Ruby`?".to_string(),
+            "Display name is used by `Ruby rings`, `Ruby rings again`".to_string(),
+            "`Ruby ring` is output exactly as much as it is input (1)".to_string(),
+        ]);
+
+        let mut untimed = Recipe { name: "Ruby rings".to_string(), ..Recipe::default() };
+        untimed.outputs = HashMap::from([("Ruby ring".to_string(), 1.0)]);
+        let severities: Vec<Severity> = validate_recipes(&[("Ruby rings".to_string(), untimed)], &name_to_id)
+            .iter()
+            .map(|issue| issue.severity)
+            .collect();
+        assert_eq!(severities, [Severity::Warning]);
+    }
+}
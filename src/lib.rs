@@ -2,6 +2,7 @@
 
 // Modules
 pub mod api;
+pub mod gp;
 pub mod file_io;
 pub mod helpers;
 pub mod item_search;
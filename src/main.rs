@@ -1,32 +1,113 @@
 //! TODO: 2025-06-16 Something weird is happening with the log...
 //! Unless the file is manually cleared, the contents will still remain
 
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use osrs_gph::{
-    api::Api, check_items_exists, config::{self, OverviewFilter}, file_io::{FileIO, FileOptions}, item_search::recipes::RecipeBook, log_match_panic, prices::prices::{PriceHandle, TimeType}, results_writer::markdown::{DetailedRecipeLookup, OptimalOverview}, types::{DetailedTable, ResultsTable, DETAILED_NUM_HEADERS, OVERVIEW_NUM_HEADERS}
+    api::{Api, ApiError, FetchMetadata, PriceResponse}, check_items_exists, config::{self, FlipOptions, OverviewFilter, PriceOverride, PricingStrategy}, file_io::{FileIO, FileOptions, SerChoice}, helpers::{format_timestamp, parse_timestamp, unix_now}, item_search::{data_types::latest::PriceDataType, history::PriceHistory, recipes::{self, RecipeBook}, validation}, log_match_panic, prices::{allocation::{self, AllocationLimits}, backtest::backtest, buy_limits::{Purchase, PurchaseLedger}, chains, flips, prices::PriceHandle, simulation::{self, DEFAULT_SIMULATION_RUNS}, tax::TaxRules}, results_writer::{markdown::{AllocationOverview, BacktestOverview, ChainOverview, DetailedRecipeLookup, FlipsOverview, OptimalOverview, SimulationOverview}, svg}, types::{DetailedTable, ResultsTable, DETAILED_NUM_HEADERS, OVERVIEW_NUM_HEADERS}
 };
-use tracing::{info, span, trace, warn, Level};
+use tracing::{error, info, span, trace, warn, Level};
 
-use clap::{Parser, builder::ArgAction};
+use clap::{Parser, Subcommand, builder::ArgAction};
 
 #[derive(Parser)]
 #[command(about, long_about = None)]
 struct Cli {
-    /// Refresh prices or not.
+    /// Default behaviour (no command): write the optimal overview and recipe lookups
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Refresh prices, regardless of the `api.refresh` policy.
     #[clap(short = 'r', long, action = ArgAction::SetTrue)]
     refresh: bool,
 
     #[clap(short = 's', long = "show-hidden", action = ArgAction::SetTrue)]
     show_hidden: bool,
 
-    // TODO: Change this to a value (number of hours to calc for)
-    //  : no value supplied means one hour; 2 hours means 2 hours etc.
+    /// Hours played, instead of `profit.session_hours`.
+    /// Recipes are made for at most this long, with the given money
+    #[clap(long)]
+    hours: Option<f32>,
+
+    /// Use the stored prices from at or before this time instead of the latest.
+    /// Unix timestamp, YYYY-MM-DD or YYYY-MM-DDTHH:MM (UTC)
+    #[clap(long = "as-of", value_parser = parse_timestamp)]
+    as_of: Option<i64>,
+
+    /// Which prices items are bought and sold at, instead of `profit.pricing`.
+    /// Recipes with their own `pricing` keep it
+    #[clap(long, value_enum)]
+    pricing: Option<PricingStrategy>,
+
+    /// Use this price for an item instead of the loaded one. Repeatable.
+    /// `Item=high/low`, or `Item=price` for both. Added to `profit.price_overrides`
+    #[clap(long = "price", value_parser = config::parse_price_override)]
+    prices: Vec<(String, PriceOverride)>,
+}
 
-    /// Calculate the amount of money made in one hour.
-    /// Default behaviour: Calculate the recipes for the maximum number of hours with the given money
-    #[clap(short = 'o', long = "one-hour", action = ArgAction::SetTrue)]
-    number_hours: bool
+#[derive(Subcommand)]
+enum Command {
+    /// Re-run every recipe against each stored price snapshot in a date range
+    Backtest {
+        /// Start of the range (inclusive). Defaults to the oldest snapshot
+        #[clap(long, value_parser = parse_timestamp)]
+        from: Option<i64>,
+        /// End of the range (inclusive). Defaults to now
+        #[clap(long, value_parser = parse_timestamp)]
+        to: Option<i64>,
+    },
+    /// Split the bankroll across several recipes, within buy limits, time and GE slots
+    Allocate {
+        /// Hours available, instead of `profit.allocation.hours`
+        #[clap(long)]
+        hours: Option<f32>,
+        /// GE slots available, instead of `profit.allocation.ge_slots`
+        #[clap(long)]
+        slots: Option<u32>,
+    },
+    /// Simulate sessions of each recipe, drawing its random drops.
+    /// Reports the spread of profit rather than only the expected profit
+    Simulate {
+        /// Recipes to simulate. Defaults to every recipe
+        recipes: Vec<String>,
+        #[clap(long, default_value_t = DEFAULT_SIMULATION_RUNS)]
+        runs: u32,
+        /// Repeat an earlier simulation. Random when not set
+        #[clap(long)]
+        seed: Option<u64>,
+    },
+    /// Rank every tradeable item by the profit of flipping its buy limit
+    Flips {
+        /// Fewest items traded per hour, instead of `profit.flips.min_hourly_volume`
+        #[clap(long)]
+        min_volume: Option<f64>,
+        /// Oldest price in minutes, instead of `profit.flips.max_price_age_mins`
+        #[clap(long)]
+        max_age: Option<u32>,
+    },
+    /// Evaluate recipes whose inputs other recipes make, deciding whether to make or buy each one
+    Chains {
+        /// Recipes to evaluate. Defaults to every recipe with a craftable input.
+        /// Recipes with `pay_once` inputs are skipped
+        recipes: Vec<String>,
+    },
+    /// Check every recipe in `lookup_data.recipes`. Exits non-zero if any has an error.
+    /// Recipes skipped at runtime, e.g. without a time, are only warned about
+    Validate,
+    /// Record a purchase against the item's buy limit
+    Bought {
+        /// Item name, as on the GE
+        item: String,
+        quantity: i32,
+        /// When it was bought. Defaults to now
+        #[clap(long, value_parser = parse_timestamp)]
+        at: Option<i64>,
+    },
+    /// Add purchases from a file to the buy limit ledger.
+    /// Same format as `filepaths.buy_ledger`
+    ImportPurchases {
+        path: String,
+    },
 }
 
 
@@ -50,6 +131,18 @@ fn main() {
     trace!(desc = "Parsing CLI arguments");
     let cli = Cli::parse();
 
+    // Only the ledger is needed, not prices
+    if let Some(command @ (Command::Bought { .. } | Command::ImportPurchases { .. })) = &cli.command {
+        update_buy_ledger(command, &conf.filepaths);
+        return;
+    }
+
+    // Only the recipes and item names are needed
+    if let Some(Command::Validate) = &cli.command {
+        let valid = run_validate(&conf.filepaths.lookup_data);
+        std::process::exit(i32::from(!valid));
+    }
+
 
     // Initialise with price data file path
     let mut file = FileIO::new(
@@ -59,15 +152,37 @@ fn main() {
 
 
     trace!(desc = "Handling refresh flag");
-    let choice: bool = cli.refresh;
-    trace!(refresh = choice);
+    let previous_fetch: Option<FetchMetadata> = load_fetch_metadata(&conf.filepaths.price_metadata);
+    let last_checked = previous_fetch.as_ref().map(|fetch| fetch.checked_at);
+    let choice: bool = cli.refresh || conf.api.refresh.should_refresh(last_checked, unix_now());
+    trace!(refresh = choice, policy = ?conf.api.refresh, last_checked = ?last_checked);
 
     // Referesh API prices
+    let mut new_prices: Option<PriceDataType> = None;
     if choice {
         let msg = "Retrieving prices from API.";
         info!(desc = msg);
         println!("{msg}");
-        request_new_prices_from_api(&conf.api, &mut file);
+        // Validators are only useful while the cached prices still exist
+        let previous = previous_fetch.as_ref()
+            .filter(|_| std::path::Path::new(&conf.filepaths.price_data).exists());
+
+        match request_new_prices_from_api(&conf.api, &mut file, previous, &conf.filepaths.price_metadata) {
+            Ok(Some(price_data)) => new_prices = Some(price_data),
+            Ok(None) => {
+                let msg = "Prices unchanged since the last refresh. Loading previous data instead.";
+                info!(desc = msg);
+                println!("{msg}");
+            }
+            Err(e) => {
+                let msg = format!(
+                    "Failed to refresh prices from API: {e}. Falling back to cached prices in `{}`.",
+                    conf.filepaths.price_data
+                );
+                warn!(desc = msg, error = ?e);
+                println!("WARNING: {msg}");
+            }
+        }
     } else {
         let msg = "Loading previous data instead.";
         info!(desc = msg);
@@ -82,15 +197,12 @@ fn main() {
 
 
 
-    trace!(desc = "Handling max-hours flag");
+    trace!(desc = "Handling hours argument");
     // Override config with new value
-    conf.display.time_type = if cli.number_hours {
-        TimeType::SingleHour
-    } else {
-        TimeType::MaxHours
-    };
-    let time_type = conf.display.time_type;
-    trace!(time_type = ?conf.display.time_type);
+    if let Some(hours) = cli.hours {
+        conf.profit.session_hours = hours;
+    }
+    trace!(session_hours = conf.profit.session_hours);
 
     // Create item search
     let mut item_search = osrs_gph::item_search::item_search::ItemSearch::new(
@@ -99,8 +211,30 @@ fn main() {
         conf.api,
     );
 
+    // Keep a snapshot of any new prices
+    if let Some(price_data) = new_prices
+        && let Err(e) = item_search.record_prices(prices_time(&price_data), &price_data) {
+            warn!(desc = "Failed to store price snapshot in history.", error = ?e);
+    }
+
+    trace!(desc = "Handling price overrides");
+    let mut price_overrides = conf.profit.price_overrides.clone();
+    price_overrides.extend(cli.prices.iter().cloned());
+    if !price_overrides.is_empty() {
+        let overrides: Vec<String> = price_overrides.iter().map(|(item, price)| format!("{item} = {price}")).collect();
+        let msg = format!("Overriding prices: {}", overrides.join(", "));
+        info!(desc = msg);
+        println!("{msg}");
+    }
+    let requested: Vec<String> = price_overrides.keys().cloned().collect();
+    item_search.set_price_overrides(price_overrides);
+    for item in requested.iter().filter(|item| !item_search.is_overridden(item)) {
+        println!("WARNING: Unknown item `{item}`. Price not overridden.");
+    }
+
     // Populate with items (from_file)
-    let item_prices = item_search.get_item_prices(true);
+    let mut item_prices = item_search.get_item_prices();
+    item_search.override_prices(&mut item_prices);
     item_search.update_item_prices(item_prices);
     let mapping_path = conf.filepaths.lookup_data.api_mapping.clone();
     log_match_panic(
@@ -109,6 +243,14 @@ fn main() {
         "Failed to update items with extra information",
     );
 
+    if let Some(liquidity) = conf.profit.liquidity {
+        let msg = "Retrieving traded volumes from API.";
+        info!(desc = msg, timespan = %liquidity.volume);
+        println!("{msg}");
+        let with_volume = item_search.update_item_volumes(liquidity.volume);
+        trace!(desc = "Updated traded volumes", items = with_volume);
+    }
+
     trace!(desc = "After update_item_prices");
     // Check important items exist in memory
     check_items_exists(&item_search, &["Coins"]);
@@ -119,22 +261,99 @@ fn main() {
     // Remove items contained in ignore_items
     item_search.ignore_items(&ignore_items);
 
+    // Shown in the header of the result files
+    let mut prices_fetched_at: Option<i64> = load_fetch_metadata(&conf.filepaths.price_metadata)
+        .map(|fetch| fetch.fetched_at);
+
+    if let Some(timestamp) = cli.as_of {
+        let resolved = log_match_panic(
+            item_search.set_prices_as_of(timestamp),
+            "Read price history",
+            "Failed to read price history",
+        );
+        if resolved.is_some() {
+            prices_fetched_at = resolved;
+        }
+        let msg = match resolved {
+            Some(fetched_at) => format!("Using prices fetched at {}.", format_timestamp(fetched_at)),
+            None => format!("No stored prices at or before {}. Using current prices.", format_timestamp(timestamp)),
+        };
+        info!(desc = msg);
+        println!("{msg}");
+    }
+
     // Load in recipes
     let mut recipe_list = RecipeBook::new(HashMap::new());
     recipe_list.load_default_recipes(conf.filepaths.lookup_data.recipes);
 
+    let pricing = cli.pricing.unwrap_or(conf.profit.pricing);
+    if conf.profit.high_alchemy {
+        let added = recipe_list.add_high_alchemy_recipes(&item_search, pricing);
+        let msg = format!("Added {added} high alchemy recipes.");
+        info!(desc = msg);
+        println!("{msg}");
+    }
+
     // Get ignored methods from the config
     let ignore_methods: Vec<String> = conf.profit.ignore_methods.clone();
     recipe_list.ignore_recipes(ignore_methods);
 
+    let tax = TaxRules::load(conf.filepaths.lookup_data.ge_tax.clone());
+
     trace!(desc = "Creating price handle...");
-    let price_handle = PriceHandle::new(
+    let mut price_handle = PriceHandle::new(
         item_search,
         recipe_list,
         conf.profit.coins,
         conf.profit.percent_margin,
+        conf.profit.stale_prices,
+        tax,
+        pricing,
     );
 
+    let buy_ledger = log_match_panic(
+        PurchaseLedger::load(&conf.filepaths.buy_ledger),
+        "Loaded buy limit ledger",
+        "Failed to load buy limit ledger",
+    );
+    price_handle.set_buy_ledger(buy_ledger, unix_now());
+    price_handle.set_session_hours(conf.profit.session_hours);
+    price_handle.set_max_volume_fraction(conf.profit.liquidity.map(|liquidity| liquidity.max_fraction));
+
+    match cli.command {
+        Some(Command::Backtest { from, to }) => {
+            let results_path = conf.filepaths.results.backtest.clone();
+            run_backtest(&mut price_handle, &conf.display, results_path, from.unwrap_or(i64::MIN), to.unwrap_or_else(unix_now));
+            return;
+        },
+        Some(Command::Allocate { hours, slots }) => {
+            let limits = AllocationLimits {
+                bankroll: conf.profit.coins,
+                hours: hours.unwrap_or(conf.profit.allocation.hours),
+                ge_slots: slots.unwrap_or(conf.profit.allocation.ge_slots),
+            };
+            run_allocation(&price_handle, &conf.display, conf.filepaths.results.allocation.clone(), limits);
+            return;
+        },
+        Some(Command::Flips { min_volume, max_age }) => {
+            let mut options = conf.profit.flips;
+            options.min_hourly_volume = min_volume.or(options.min_hourly_volume);
+            options.max_price_age_mins = max_age.unwrap_or(options.max_price_age_mins);
+            run_flips(&mut price_handle, &conf.display, conf.filepaths.results.flips.clone(), options, prices_fetched_at);
+            return;
+        },
+        Some(Command::Simulate { recipes, runs, seed }) => {
+            let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+            run_simulation(&price_handle, &conf.display, conf.filepaths.results.simulation.clone(), &recipes, runs, seed);
+            return;
+        },
+        Some(Command::Chains { recipes }) => {
+            run_chains(&price_handle, &conf.display, conf.filepaths.results.chains.clone(), &recipes, prices_fetched_at);
+            return;
+        },
+        Some(Command::Bought { .. } | Command::ImportPurchases { .. } | Command::Validate) | None => {},
+    }
+
     trace!(desc = "Computing weights for pareto sort...");
     let weights = osrs_gph::prices::pareto_sort::custom_types::compute_weights(
         conf.profit.coins,
@@ -146,6 +365,20 @@ fn main() {
     let optimal_overview = price_handle.all_recipe_overview(&sort_by, &weights, &conf.display);
     assert!(!optimal_overview.is_empty());
 
+    // Read once, for the profit charts of each recipe and the price charts of their items
+    let price_history: Option<PriceHistory> = conf.display.charts.then(|| {
+        price_handle.all_items.history.load_between(i64::MIN, i64::MAX).unwrap_or_else(|e| {
+            warn!(desc = "Failed to read price history for charts.", error = ?e);
+            PriceHistory::default()
+        })
+    });
+
+    // Profit charts of each recipe, from the stored price history
+    let recipe_charts: HashMap<String, PathBuf> = price_history.as_ref()
+        .filter(|_| conf.display.profit_charts)
+        .map(|history| write_recipe_charts(&mut price_handle, history, &conf.display, &conf.filepaths.results.charts))
+        .unwrap_or_default();
+
     trace!(desc = "Changing file path to optimal overview results file");
     // Write out to file
     file.set_file_path(conf.filepaths.results.optimal.clone());
@@ -159,6 +392,13 @@ fn main() {
     trace!(desc = "Writing overview to file");
     // TODO: Possible to take reference to optimal_overview instead?
     let mut writer = OptimalOverview::new(optimal_overview.clone(), [0; OVERVIEW_NUM_HEADERS]);
+    writer.set_prices_fetched_at(prices_fetched_at);
+    writer.set_pricing(price_handle.pricing);
+    writer.set_chart_links(
+        recipe_charts.iter()
+            .map(|(name, path)| (name.clone(), svg::link_path(&conf.filepaths.results.optimal, path)))
+            .collect()
+    );
 
     // TODO: Optimise into reduced/buffered calls?
     // Set append mode since all rows are written in separate calls
@@ -188,7 +428,7 @@ fn main() {
                 // E.g. if `*` is appended to the name due to filters
                 let recipe_s = row.name.clone();
                 let x = price_handle.recipe_list.get_recipe(&recipe_s)?;
-                let specific_lookup = price_handle.recipe_lookup_from_recipe(x, time_type)?;
+                let specific_lookup = price_handle.recipe_lookup_from_recipe(x)?;
                 Some(specific_lookup)
             })
             .collect();
@@ -201,7 +441,7 @@ fn main() {
         .into_iter()
         .filter_map(|recipe_s| {
             let x = price_handle.recipe_list.get_recipe(&recipe_s)?;
-            let specific_lookup = price_handle.recipe_lookup_from_recipe(x, time_type)?;
+            let specific_lookup = price_handle.recipe_lookup_from_recipe(x)?;
             Some(specific_lookup)
         })
         .collect();
@@ -212,6 +452,23 @@ fn main() {
     recipe_lookup_list.sort_by_key(|e| e.overview.name.clone());
     recipe_lookup_list.dedup_by_key(|e| e.overview.name.clone());
 
+    if let Some(timestep) = conf.display.lookup.timeseries {
+        let msg = "Retrieving recent price history from API.";
+        info!(desc = msg, timestep = %timestep);
+        println!("{msg}");
+        price_handle.add_price_history(&mut recipe_lookup_list, timestep);
+    }
+
+    if let Some(history) = &price_history {
+        add_lookup_chart_links(
+            &price_handle,
+            history,
+            &mut recipe_lookup_list,
+            &recipe_charts,
+            &conf.filepaths.results,
+        );
+    }
+
 
     trace!(desc = "Creating DetailedRecipeLookup struct");
     let mut writer = DetailedRecipeLookup::new(
@@ -219,6 +476,7 @@ fn main() {
         recipe_lookup_list,
         [0;DETAILED_NUM_HEADERS]
     );
+    writer.set_prices_fetched_at(prices_fetched_at);
     trace!(desc = "Changing file path to recipe lookup results file");
     // Write out to file
     file.set_file_path(conf.filepaths.results.lookup.clone());
@@ -241,9 +499,392 @@ fn main() {
     );
 }
 
-fn request_new_prices_from_api(api_settings: &config::Api, file: &mut FileIO) {
+/// Writes a profit chart for every recipe with stored prices
+/// Returns the recipe name -> chart path of each written chart
+fn write_recipe_charts(price_handle: &mut PriceHandle, history: &PriceHistory, display: &config::Display, charts_dir: &str) -> HashMap<String, PathBuf> {
+    trace!(desc = "Writing recipe profit charts");
+    backtest(price_handle, history, &display.membership).iter()
+        .filter_map(|series| {
+            let chart = svg::recipe_profit_chart(series);
+            let file_name = format!("recipe_{}.svg", svg::slug(&series.name));
+            match chart.write_to(charts_dir, &file_name) {
+                Ok(path) => Some((series.name.clone(), path)),
+                Err(e) => {
+                    warn!(desc = "Failed to write chart.", recipe = %series.name, error = ?e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Writes a price chart for every item in the recipe lookups,
+/// then links them (and the recipe's profit chart) from each table
+fn add_lookup_chart_links(
+    price_handle: &PriceHandle,
+    history: &PriceHistory,
+    tables: &mut [DetailedTable],
+    recipe_charts: &HashMap<String, PathBuf>,
+    results_paths: &config::ResultsPaths,
+) {
+    trace!(desc = "Writing item price charts");
+    let item_search = &price_handle.all_items;
+    let item_names = |table: &DetailedTable| -> Vec<String> {
+        table.inputs.pay_once.iter().flatten()
+            .chain(&table.inputs.inputs)
+            .chain(&table.outputs)
+            .map(|(name, _, _)| name.clone())
+            .collect()
+    };
+
+    let mut item_ids: Vec<&String> = tables.iter()
+        .flat_map(item_names)
+        .filter_map(|name| item_search.name_to_id.get(&name))
+        .collect();
+    item_ids.sort_unstable();
+    item_ids.dedup();
+
+    let mut item_charts: HashMap<String, PathBuf> = HashMap::new();
+    for item_id in item_ids {
+        let item_history = history.item(item_id);
+        if item_history.is_empty() {
+            continue;
+        }
+        let Some(item_name) = item_search.name_from_id(item_id) else { continue };
+        let chart = svg::item_price_chart(item_name, item_history);
+        let file_name = format!("item_{}.svg", svg::slug(item_name));
+        match chart.write_to(&results_paths.charts, &file_name) {
+            Ok(path) => { item_charts.insert(item_name.clone(), path); },
+            Err(e) => warn!(desc = "Failed to write chart.", item = %item_name, error = ?e),
+        }
+    }
+
+    for table in tables {
+        let recipe_link = recipe_charts.get(&table.overview.name)
+            .map(|path| ("Profit".to_string(), svg::link_path(&results_paths.lookup, path)));
+
+        let item_links: Vec<(String, String)> = item_names(table).into_iter()
+            .filter_map(|name| {
+                let link = svg::link_path(&results_paths.lookup, item_charts.get(&name)?);
+                Some((name, link))
+            })
+            .collect();
+
+        table.chart_links = recipe_link.into_iter().chain(item_links).collect();
+    }
+}
+
+fn run_backtest(price_handle: &mut PriceHandle, display: &config::Display, results_path: String, from: i64, to: i64) {
+    let msg = "Backtesting recipes against stored prices.";
+    info!(desc = msg, from, to);
+    println!("{msg}");
+
+    let history = log_match_panic(
+        price_handle.all_items.history.load_between(from, to),
+        "Read price history",
+        "Failed to read price history",
+    );
+    let all_series = backtest(price_handle, &history, &display.membership);
+
+    // Start of the range actually covered
+    let from = all_series.iter()
+        .filter_map(|series| series.points.first())
+        .map(|point| point.fetched_at)
+        .min()
+        .map_or(from, |first| first.max(from));
+
+    let mut file = FileIO::new(results_path, FileOptions::new(true, true, true));
+    log_match_panic(
+        file.clear_contents(),
+        "Cleared file contents",
+        "Failed to clear file contents",
+    );
+
+    trace!(desc = "Writing backtest to file");
+    let mut writer = BacktestOverview::new(from, to, all_series);
+    file = file.set_append(true);
+    log_match_panic(
+        writer.write_all_tables(&mut file),
+        "Wrote backtest to file",
+        "Failed to write backtest",
+    );
+}
+
+/// Record or import purchases, forgetting any whose buy limit has since reset
+fn update_buy_ledger(command: &Command, filepaths: &config::FilePaths) {
+    let now = unix_now();
+    let mut ledger = log_match_panic(
+        PurchaseLedger::load(&filepaths.buy_ledger),
+        "Loaded buy limit ledger",
+        "Failed to load buy limit ledger",
+    );
+    ledger.prune(now);
+
+    let mut name_file = FileIO::new(filepaths.lookup_data.name_to_id.clone(), FileOptions::new(true, false, false));
+    let name_to_id: HashMap<String, String> = log_match_panic(
+        name_file.read_serialized(SerChoice::YAML),
+        "Read item names",
+        "Failed to read item names",
+    );
+    let unknown_item = |item: &str| {
+        let unknown = !name_to_id.contains_key(item);
+        if unknown {
+            let msg = format!("Unknown item `{item}`. Not recorded.");
+            warn!(desc = msg);
+            println!("WARNING: {msg}");
+        }
+        unknown
+    };
+
+    let msg = match command {
+        Command::Bought { item, quantity, at } => {
+            if unknown_item(item) {
+                return;
+            }
+            ledger.record(Purchase { item: item.clone(), quantity: *quantity, bought_at: at.unwrap_or(now) });
+            format!("Recorded {quantity} x {item}.")
+        },
+        Command::ImportPurchases { path } => {
+            let mut imported = log_match_panic(
+                PurchaseLedger::load(path),
+                "Read purchases to import",
+                "Failed to read purchases to import",
+            );
+            imported.purchases.retain(|purchase| !unknown_item(&purchase.item));
+            imported.prune(now);
+            format!("Imported {} purchases.", ledger.import(imported))
+        },
+        Command::Backtest { .. } | Command::Allocate { .. } | Command::Simulate { .. } | Command::Flips { .. } | Command::Chains { .. } | Command::Validate => return,
+    };
+
+    log_match_panic(
+        ledger.save(&filepaths.buy_ledger),
+        "Saved buy limit ledger",
+        "Failed to save buy limit ledger",
+    );
+    info!(desc = msg);
+    println!("{msg}");
+}
+
+fn run_allocation(price_handle: &PriceHandle, display: &config::Display, results_path: String, limits: AllocationLimits) {
+    let msg = "Allocating coins across recipes.";
+    info!(desc = msg, ?limits);
+    println!("{msg}");
+
+    let plan = allocation::plan(price_handle, limits, &display.membership);
+
+    let mut file = FileIO::new(results_path, FileOptions::new(true, true, true));
+    log_match_panic(
+        file.clear_contents(),
+        "Cleared file contents",
+        "Failed to clear file contents",
+    );
+
+    trace!(desc = "Writing allocation to file");
+    let mut writer = AllocationOverview::new(plan);
+    file = file.set_append(true);
+    log_match_panic(
+        writer.write_all_tables(&mut file),
+        "Wrote allocation to file",
+        "Failed to write allocation",
+    );
+}
+
+fn run_flips(price_handle: &mut PriceHandle, display: &config::Display, results_path: String, mut options: FlipOptions, prices_fetched_at: Option<i64>) {
+    let msg = "Scanning items to flip.";
+    info!(desc = msg, ?options);
+    println!("{msg}");
+
+    // Volumes are already loaded for `profit.liquidity`
+    if options.min_hourly_volume.is_some() && price_handle.all_items.hourly_volumes.is_empty() {
+        let msg = "Retrieving traded volumes from API.";
+        info!(desc = msg, timespan = %options.volume);
+        println!("{msg}");
+        let with_volume = price_handle.all_items.update_item_volumes(options.volume);
+        trace!(desc = "Updated traded volumes", items = with_volume);
+
+        if with_volume == 0 {
+            let msg = "No traded volumes. Not filtering by volume.";
+            warn!(desc = msg);
+            println!("WARNING: {msg}");
+            options.min_hourly_volume = None;
+        }
+    }
+
+    let mut all_flips = flips::scan(price_handle, &options, &display.membership);
+    if display.number > 0 {
+        all_flips.truncate(display.number as usize);
+    }
+
+    let mut file = FileIO::new(results_path, FileOptions::new(true, true, true));
+    log_match_panic(
+        file.clear_contents(),
+        "Cleared file contents",
+        "Failed to clear file contents",
+    );
+
+    trace!(desc = "Writing flips to file");
+    let mut writer = FlipsOverview::new(all_flips, prices_fetched_at);
+    file = file.set_append(true);
+    log_match_panic(
+        writer.write_all_tables(&mut file),
+        "Wrote flips to file",
+        "Failed to write flips",
+    );
+}
+
+fn run_simulation(price_handle: &PriceHandle, display: &config::Display, results_path: String, recipes: &[String], runs: u32, seed: u64) {
+    let msg = "Simulating recipe sessions.";
+    info!(desc = msg, runs, seed);
+    println!("{msg}");
+
+    let unknown: Vec<&String> = recipes.iter()
+        .filter(|name| price_handle.recipe_list.get_recipe(name).is_none())
+        .collect();
+    if !unknown.is_empty() {
+        warn!(desc = "Unknown recipes to simulate", ?unknown);
+        println!("WARNING: Unknown recipes {unknown:?}");
+    }
+
+    let simulation = simulation::simulate(price_handle, recipes, &display.membership, runs, seed);
+
+    let mut file = FileIO::new(results_path, FileOptions::new(true, true, true));
+    log_match_panic(
+        file.clear_contents(),
+        "Cleared file contents",
+        "Failed to clear file contents",
+    );
+
+    trace!(desc = "Writing simulation to file");
+    let mut writer = SimulationOverview::new(simulation);
+    file = file.set_append(true);
+    log_match_panic(
+        writer.write_all_tables(&mut file),
+        "Wrote simulation to file",
+        "Failed to write simulation",
+    );
+}
+
+/// Print every issue with the recipes. Returns false if there are any
+fn run_validate(lookup_data: &config::LookupDataPaths) -> bool {
+    let msg = format!("Validating recipes in `{}`.", lookup_data.recipes);
+    info!(desc = msg);
+    println!("{msg}");
+
+    let recipes = match recipes::read_recipe_entries(lookup_data.recipes.clone()) {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            error!(desc = "Failed to read recipes", error = ?e);
+            println!("error: {e}");
+            return false;
+        },
+    };
+
+    let mut file = FileIO::new(lookup_data.name_to_id.clone(), FileOptions::new(true, false, false));
+    let name_to_id: HashMap<String, String> = log_match_panic(
+        file.read_serialized(SerChoice::YAML),
+        "Reading name_to_id lookup data",
+        "Failed to Deserialize name_to_id",
+    );
+
+    let issues = validation::validate_recipes(&recipes, &name_to_id);
+    for issue in &issues {
+        match issue.severity {
+            validation::Severity::Error => {
+                warn!(desc = "Invalid recipe", %issue);
+                println!("error: {issue}");
+            },
+            validation::Severity::Warning => {
+                warn!(desc = "Recipe will be skipped", %issue);
+                println!("warning: {issue}");
+            },
+        }
+    }
+
+    let errors = issues.iter().filter(|issue| issue.severity == validation::Severity::Error).count();
+    let msg = format!("Checked {} recipes: {errors} errors, {} warnings.", recipes.len(), issues.len() - errors);
+    info!(desc = msg);
+    println!("{msg}");
+
+    errors == 0
+}
+
+fn run_chains(price_handle: &PriceHandle, display: &config::Display, results_path: String, recipes: &[String], prices_fetched_at: Option<i64>) {
+    let msg = "Evaluating recipe chains.";
+    info!(desc = msg);
+    println!("{msg}");
+
+    let unknown: Vec<&String> = recipes.iter()
+        .filter(|name| price_handle.recipe_list.get_recipe(name).is_none())
+        .collect();
+    if !unknown.is_empty() {
+        warn!(desc = "Unknown recipes to evaluate", ?unknown);
+        println!("WARNING: Unknown recipes {unknown:?}");
+    }
+
+    let mut all_chains = chains::evaluate_chains(price_handle, recipes, &display.membership);
+    if display.number > 0 {
+        all_chains.truncate(display.number as usize);
+    }
+
+    let mut file = FileIO::new(results_path, FileOptions::new(true, true, true));
+    log_match_panic(
+        file.clear_contents(),
+        "Cleared file contents",
+        "Failed to clear file contents",
+    );
+
+    trace!(desc = "Writing chains to file");
+    let mut writer = ChainOverview::new(all_chains, prices_fetched_at);
+    file = file.set_append(true);
+    log_match_panic(
+        writer.write_all_tables(&mut file),
+        "Wrote chains to file",
+        "Failed to write chains",
+    );
+}
+
+/// Fetch time of the stored prices. `None` if they were never fetched or unreadable
+fn load_fetch_metadata(path: &str) -> Option<FetchMetadata> {
+    if !std::path::Path::new(path).exists() {
+        return None;
+    }
+
+    let mut metadata_file = FileIO::new(path.to_string(), FileOptions::new(true, false, false));
+    metadata_file.read_serialized(SerChoice::YAML)
+        .inspect_err(|e| warn!(desc = "Failed to read price fetch metadata.", error = ?e))
+        .ok()
+}
+
+fn store_fetch_metadata(path: &str, metadata: &FetchMetadata) {
+    let mut metadata_file = FileIO::new(path.to_string(), FileOptions::new(true, true, true));
+    if let Err(e) = metadata_file.clear_contents().and_then(|()| metadata_file.write_serialized(metadata)) {
+        warn!(desc = "Failed to store price fetch metadata.", error = ?e);
+    }
+}
+
+/// Request new prices and overwrite the cached price data `file`.
+/// Returns `None` when the prices are unchanged since the `previous` fetch.
+/// The cache is left untouched when the request fails.
+fn request_new_prices_from_api(
+    api_settings: &config::Api,
+    file: &mut FileIO,
+    previous: Option<&FetchMetadata>,
+    metadata_path: &str,
+) -> Result<Option<PriceDataType>, ApiError> {
     let api = Api::new(api_settings);
-    let price_data = api.request_item_prices();
+    let now = unix_now();
+
+    let validators = previous.map(|fetch| fetch.validators.clone()).unwrap_or_default();
+
+    let (price_data, validators) = match api.request_item_prices_if_modified(&validators)? {
+        PriceResponse::Modified(price_data, validators) => (price_data, validators),
+        PriceResponse::NotModified => {
+            let fetched_at = previous.map_or(now, |fetch| fetch.fetched_at);
+            store_fetch_metadata(metadata_path, &FetchMetadata { fetched_at, checked_at: now, validators });
+            return Ok(None);
+        }
+    };
 
     // TODO: Should this be fatal?
     if let Err(e) = file.clear_contents() {
@@ -255,5 +896,13 @@ fn request_new_prices_from_api(api_settings: &config::Api, file: &mut FileIO) {
         "Write success.",
         "Failed to write to file.",
     );
+    store_fetch_metadata(metadata_path, &FetchMetadata { fetched_at: prices_time(&price_data), checked_at: now, validators });
+
+    Ok(Some(price_data))
+}
+
+/// Time the prices are from: the start of the window for averaged prices, otherwise now
+fn prices_time(price_data: &PriceDataType) -> i64 {
+    price_data.timestamp.map_or_else(unix_now, i64::from)
 }
 
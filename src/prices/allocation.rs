@@ -0,0 +1,269 @@
+//! Splitting the bankroll across several recipes
+use std::collections::{HashMap, HashSet};
+
+use tracing::{debug, instrument};
+
+use crate::{
+    config::Membership,
+    gp::Gp,
+    item_search::data_types::latest::SPECIAL_ITEM_NAMES,
+};
+
+use super::prices::PriceHandle;
+
+/// Limits shared by every recipe in a plan
+#[derive(Debug, Clone, Copy)]
+pub struct AllocationLimits {
+    pub bankroll: Gp,
+    pub hours: f32,
+    pub ge_slots: u32,
+}
+
+/// A recipe chosen by the planner
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub name: String,
+    pub number: i32,
+    /// Coins spent on inputs, including items paid for once
+    pub cost: Gp,
+    /// Total profit, after paying for items once
+    pub profit: Gp,
+    pub time_sec: f64,
+}
+
+impl Allocation {
+    pub fn gph(&self) -> Gp {
+        self.profit.per_hour(self.time_sec)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AllocationPlan {
+    pub limits: AllocationLimits,
+    pub allocations: Vec<Allocation>,
+    /// Distinct items traded across all allocations
+    pub ge_slots_used: u32,
+}
+
+impl AllocationPlan {
+    pub fn total_cost(&self) -> Gp {
+        self.allocations.iter().map(|a| a.cost).sum()
+    }
+
+    pub fn total_profit(&self) -> Gp {
+        self.allocations.iter().map(|a| a.profit).sum()
+    }
+
+    pub fn total_time_sec(&self) -> f64 {
+        self.allocations.iter().map(|a| a.time_sec).sum()
+    }
+
+    pub fn gph(&self) -> Gp {
+        self.total_profit().per_hour(self.total_time_sec())
+    }
+}
+
+/// A profitable recipe that could be part of a plan
+#[derive(Debug, Clone)]
+struct Candidate {
+    name: String,
+    single_cost: Gp,
+    single_profit: Gp,
+    pay_once: Gp,
+    time_sec: f64,
+    // Input item name -> (remaining buy limit, quantity per recipe)
+    limits: Vec<(String, Option<i32>, f32)>,
+    // Every item bought or sold; each needs a GE slot
+    traded_items: HashSet<String>,
+}
+
+impl Candidate {
+    fn gph(&self) -> f64 {
+        self.single_profit.to_f64() / self.time_sec
+    }
+
+    fn profit_per_coin(&self) -> f64 {
+        if self.single_cost == Gp::ZERO {
+            f64::INFINITY
+        } else {
+            self.single_profit.to_f64() / self.single_cost.to_f64()
+        }
+    }
+}
+
+fn candidates(price_handle: &PriceHandle, membership: &Membership) -> Vec<Candidate> {
+    let mut recipes: Vec<_> = price_handle.recipe_list
+        .get_all_recipes()
+        .into_values()
+        .filter(|recipe| !membership.excludes(recipe.members))
+        .collect();
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    recipes.iter()
+        .filter_map(|recipe| {
+            let (overview, (cost, _)) = price_handle.recipe_price_overview_from_recipe(recipe)?;
+            let time_sec = f64::from(overview.time_sec?);
+            if overview.profit <= Gp::ZERO || time_sec <= 0.0 {
+                return None;
+            }
+
+            let pricing = price_handle.recipe_pricing(recipe);
+            let inputs = price_handle.parse_item_list(&recipe.inputs.inputs, true, pricing)?;
+            let pay_once = match &recipe.inputs.pay_once {
+                Some(items) => price_handle.parse_item_list(items, true, pricing)?,
+                None => Vec::new(),
+            };
+
+            let traded_items = inputs.iter().chain(&pay_once)
+                .map(|(item, _)| item.name.clone())
+                .chain(recipe.expected_outputs().into_keys())
+                .filter(|name| !SPECIAL_ITEM_NAMES.contains(&name.as_str()))
+                .collect();
+
+            Some(Candidate {
+                name: recipe.name.clone(),
+                single_cost: cost,
+                single_profit: overview.profit,
+                pay_once: overview.pay_once_total.unwrap_or(Gp::ZERO),
+                time_sec,
+                limits: inputs.iter().map(|(item, quantity)| (item.name.clone(), price_handle.remaining_limit(item), *quantity)).collect(),
+                traded_items,
+            })
+        })
+        .collect()
+}
+
+/// Take as many of each candidate as the remaining limits allow, in order
+fn fill(ordered: &[&Candidate], limits: AllocationLimits) -> AllocationPlan {
+    let mut coins = limits.bankroll;
+    let mut seconds = f64::from(limits.hours) * 3600.0;
+    // Item name -> number already bought, counted against its buy limit
+    let mut bought: HashMap<&str, f64> = HashMap::new();
+    let mut slots: HashSet<&str> = HashSet::new();
+    let mut allocations = Vec::new();
+
+    for candidate in ordered {
+        let new_slots = candidate.traded_items.iter()
+            .filter(|item| !slots.contains(item.as_str()))
+            .count();
+        if slots.len() + new_slots > limits.ge_slots as usize {
+            debug!(desc = "Not enough GE slots", recipe = %candidate.name, new_slots);
+            continue;
+        }
+
+        let spendable = coins - candidate.pay_once;
+        if spendable < Gp::ZERO {
+            continue;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let by_time = (seconds / candidate.time_sec).floor() as i64;
+        let by_coins = spendable.affordable(candidate.single_cost).unwrap_or(i64::MAX);
+        #[allow(clippy::cast_possible_truncation)]
+        let by_limit = candidate.limits.iter()
+            .filter_map(|(name, limit, quantity)| {
+                let left = f64::from((*limit)?) - bought.get(name.as_str()).copied().unwrap_or(0.0);
+                Some((left / f64::from(*quantity)).floor().max(0.0) as i64)
+            })
+            .min()
+            .unwrap_or(i64::MAX);
+
+        let number = by_time.min(by_coins).min(by_limit).min(i64::from(i32::MAX));
+        if number <= 0 {
+            continue;
+        }
+        // Too few to earn back the items paid for once
+        if candidate.single_profit.times(number) <= candidate.pay_once {
+            debug!(desc = "Profit does not cover items paid once", recipe = %candidate.name, number);
+            continue;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let number_f64 = number as f64;
+        for (name, _, quantity) in &candidate.limits {
+            *bought.entry(name.as_str()).or_default() += number_f64 * f64::from(*quantity);
+        }
+        slots.extend(candidate.traded_items.iter().map(String::as_str));
+
+        let cost = candidate.single_cost.times(number) + candidate.pay_once;
+        let time_sec = number_f64 * candidate.time_sec;
+        coins -= cost;
+        seconds -= time_sec;
+
+        allocations.push(Allocation {
+            name: candidate.name.clone(),
+            // SAFETY: capped at i32::MAX above
+            number: i32::try_from(number).unwrap_or(i32::MAX),
+            cost,
+            profit: candidate.single_profit.times(number) - candidate.pay_once,
+            time_sec,
+        });
+    }
+
+    AllocationPlan {
+        limits,
+        allocations,
+        ge_slots_used: u32::try_from(slots.len()).unwrap_or(u32::MAX),
+    }
+}
+
+/// Choose recipes and how many of each to make, for the most total profit.
+/// Every recipe shares the bankroll, hours, GE slots and each input item's buy limit.
+/// Coins are not reinvested after selling.
+///
+/// Greedy: recipes are filled by highest GP/h first, and by highest profit per coin spent.
+/// The more profitable of the two plans is kept.
+#[instrument(level = "debug", skip(price_handle, membership))]
+pub fn plan(price_handle: &PriceHandle, limits: AllocationLimits, membership: &Membership) -> AllocationPlan {
+    let candidates = candidates(price_handle, membership);
+
+    let mut by_gph: Vec<&Candidate> = candidates.iter().collect();
+    by_gph.sort_by(|a, b| b.gph().total_cmp(&a.gph()));
+
+    let mut by_profit_per_coin: Vec<&Candidate> = candidates.iter().collect();
+    by_profit_per_coin.sort_by(|a, b| b.profit_per_coin().total_cmp(&a.profit_per_coin()));
+
+    let gph_plan = fill(&by_gph, limits);
+    let coin_plan = fill(&by_profit_per_coin, limits);
+    debug!(
+        desc = "Allocation plans",
+        gph_profit = %gph_plan.total_profit(),
+        coin_profit = %coin_plan.total_profit()
+    );
+
+    if coin_plan.total_profit() > gph_plan.total_profit() {
+        coin_plan
+    } else {
+        gph_plan
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidate(name: &str, single_profit: i64, pay_once: i64, limit: i32) -> Candidate {
+        Candidate {
+            name: name.to_string(),
+            single_cost: Gp::new(100),
+            single_profit: Gp::new(single_profit),
+            pay_once: Gp::new(pay_once),
+            time_sec: 1.0,
+            limits: vec![(format!("{name} input"), Some(limit), 1.0)],
+            traded_items: HashSet::from([format!("{name} input")]),
+        }
+    }
+
+    #[test]
+    fn skips_recipes_not_covering_pay_once() {
+        // 10 at most, earning 500 against a 1,000 coin tool
+        let tool = candidate("Tool", 50, 1_000, 10);
+        let plain = candidate("Plain", 10, 0, 10);
+        let limits = AllocationLimits { bankroll: Gp::new(10_000), hours: 1.0, ge_slots: 8 };
+
+        let plan = fill(&[&tool, &plain], limits);
+        let chosen: Vec<_> = plan.allocations.iter().map(|a| (a.name.as_str(), a.number)).collect();
+        assert_eq!(chosen, [("Plain", 10)]);
+        assert_eq!(plan.total_profit(), Gp::new(100));
+    }
+}
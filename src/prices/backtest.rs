@@ -0,0 +1,128 @@
+//! Re-running recipe overviews against stored price snapshots
+use tracing::{debug, instrument, warn};
+
+use crate::{config::Membership, gp::Gp, item_search::history::PriceHistory, types::OverviewRow};
+
+use super::prices::PriceHandle;
+
+/// A single recipe overview at one price snapshot
+#[derive(Debug, Clone)]
+pub struct BacktestPoint {
+    pub fetched_at: i64, // Unix Timestamp
+    pub overview: OverviewRow,
+}
+
+/// All overviews of a recipe across the backtested snapshots
+#[derive(Debug, Clone, Default)]
+pub struct BacktestSeries {
+    pub name: String,
+    pub points: Vec<BacktestPoint>,
+}
+
+/// Mean, (population) standard deviation and worst case of a series
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SeriesStats {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub worst: Gp,
+}
+
+impl SeriesStats {
+    pub fn from_values(values: &[Gp]) -> Option<Self> {
+        let worst = *values.iter().min()?;
+
+        #[allow(clippy::cast_precision_loss)]
+        let count = values.len() as f64;
+        let mean = values.iter().copied().map(Gp::to_f64).sum::<f64>() / count;
+        let variance = values.iter()
+            .map(|v| (v.to_f64() - mean).powi(2))
+            .sum::<f64>() / count;
+
+        Some(Self {
+            mean,
+            std_dev: variance.sqrt(),
+            worst,
+        })
+    }
+}
+
+impl BacktestSeries {
+    pub fn profit_stats(&self) -> Option<SeriesStats> {
+        let values: Vec<Gp> = self.points.iter().map(|p| p.overview.total_gp()).collect();
+        SeriesStats::from_values(&values)
+    }
+
+    pub fn gph_stats(&self) -> Option<SeriesStats> {
+        let values: Vec<Gp> = self.points.iter().map(|p| p.overview.gph()).collect();
+        SeriesStats::from_values(&values)
+    }
+}
+
+/// Re-run [`PriceHandle::recipe_price_overview_from_recipe`] for every recipe
+/// at every snapshot in `history`. See [`crate::item_search::history::HistoryStore::load_between`]
+/// Each snapshot is priced without price overrides, and with the buy limits used as of its fetch time.
+/// Current prices, overrides and ledger time are restored afterwards.
+/// Sorted by highest mean total profit first.
+#[instrument(level = "debug", skip_all)]
+pub fn backtest(price_handle: &mut PriceHandle, history: &PriceHistory, membership: &Membership) -> Vec<BacktestSeries> {
+    if history.is_empty() {
+        warn!(desc = "No stored snapshots within the backtest range");
+    }
+
+    let mut recipes: Vec<_> = price_handle.recipe_list
+        .get_all_recipes()
+        .into_values()
+        .filter(|recipe| !membership.excludes(recipe.members))
+        .collect();
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let current_items = price_handle.all_items.items.clone();
+    let current_trade_time = price_handle.all_items.latest_trade_time;
+    let current_ledger_time = price_handle.ledger_time;
+    // What-ifs for today's prices say nothing about past ones
+    let overrides = std::mem::take(&mut price_handle.all_items.price_overrides);
+
+    let mut all_series: Vec<BacktestSeries> = recipes.iter()
+        .map(|recipe| BacktestSeries { name: recipe.name.clone(), points: Vec::new() })
+        .collect();
+
+    for (fetched_at, prices) in history.snapshots() {
+        price_handle.all_items.set_prices(prices.clone());
+        price_handle.ledger_time = *fetched_at;
+        debug!(desc = "Backtesting snapshot", fetched_at);
+
+        for (recipe, series) in recipes.iter().zip(all_series.iter_mut()) {
+            if let Some((overview, _)) = price_handle.recipe_price_overview_from_recipe(recipe) {
+                series.points.push(BacktestPoint { fetched_at: *fetched_at, overview });
+            }
+        }
+    }
+
+    price_handle.all_items.items = current_items;
+    price_handle.all_items.latest_trade_time = current_trade_time;
+    price_handle.ledger_time = current_ledger_time;
+    price_handle.all_items.price_overrides = overrides;
+
+    all_series.retain(|series| !series.points.is_empty());
+
+    let mean_profit = |series: &BacktestSeries| series.profit_stats().map_or(f64::MIN, |s| s.mean);
+    all_series.sort_by(|a, b| mean_profit(b).total_cmp(&mean_profit(a)));
+
+    all_series
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stats_of_series() {
+        let values = [2, 4, 4, 4, 5, 5, 7, 9].map(Gp::new);
+        let stats = SeriesStats::from_values(&values).unwrap();
+        assert!((stats.mean - 5.0).abs() < f64::EPSILON);
+        assert!((stats.std_dev - 2.0).abs() < f64::EPSILON);
+        assert_eq!(stats.worst, Gp::new(2));
+
+        assert!(SeriesStats::from_values(&[]).is_none());
+    }
+}
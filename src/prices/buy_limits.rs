@@ -0,0 +1,137 @@
+//! Local ledger of GE purchases, to track each item's buy limit across sessions
+//! <https://oldschool.runescape.wiki/w/Grand_Exchange#Trade_restrictions>
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::file_io::{FileIO, FileOptions, SerChoice};
+
+/// A buy limit resets this long after the first purchase of the window
+pub const BUY_LIMIT_WINDOW_SECS: i64 = 4 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Purchase {
+    pub item: String,
+    pub quantity: i32,
+    /// Unix timestamp
+    pub bought_at: i64,
+}
+
+/// Stored in `filepaths.buy_ledger`. Purchases can also be imported from a file of the same format
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PurchaseLedger {
+    #[serde(default)]
+    pub purchases: Vec<Purchase>,
+}
+
+impl PurchaseLedger {
+    /// Empty if there is no ledger at `path` yet
+    /// # Errors
+    /// Refer to [`FileIO::read_serialized`]
+    pub fn load(path: &str) -> Result<Self, std::io::Error> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+
+        let mut ledger_file = FileIO::new(path.to_string(), FileOptions::new(true, false, false));
+        ledger_file.read_serialized(SerChoice::YAML)
+    }
+
+    /// # Errors
+    /// Refer to [`FileIO::clear_contents`] and [`FileIO::write_serialized`]
+    pub fn save(&self, path: &str) -> Result<(), std::io::Error> {
+        let mut ledger_file = FileIO::new(path.to_string(), FileOptions::new(true, true, true));
+        ledger_file.clear_contents()?;
+        ledger_file.write_serialized(self)
+    }
+
+    pub fn record(&mut self, purchase: Purchase) {
+        self.purchases.push(purchase);
+    }
+
+    /// Add purchases from `other`, skipping any already recorded.
+    /// Returns the number added
+    pub fn import(&mut self, other: Self) -> usize {
+        let before = self.purchases.len();
+        for purchase in other.purchases {
+            if !self.purchases.contains(&purchase) {
+                self.purchases.push(purchase);
+            }
+        }
+        self.purchases.len() - before
+    }
+
+    /// Forget purchases from windows that have already reset
+    pub fn prune(&mut self, now: i64) {
+        let window_starts: Vec<Option<i64>> = self.purchases.iter()
+            .map(|purchase| self.window(&purchase.item, now).map(|(_, resets_at)| resets_at - BUY_LIMIT_WINDOW_SECS))
+            .collect();
+
+        let mut window_starts = window_starts.into_iter();
+        self.purchases.retain(|purchase| {
+            let start = window_starts.next().flatten();
+            purchase.bought_at > now || start.is_some_and(|start| purchase.bought_at >= start)
+        });
+    }
+
+    /// Quantity of `item` bought in its current window, and when the window resets.
+    /// `None` if the limit has not been used since it last reset
+    pub fn window(&self, item: &str, now: i64) -> Option<(i32, i64)> {
+        let mut purchases: Vec<&Purchase> = self.purchases.iter()
+            .filter(|purchase| purchase.item == item && purchase.bought_at <= now)
+            .collect();
+        purchases.sort_by_key(|purchase| purchase.bought_at);
+
+        // Window start, quantity bought since
+        let mut window: Option<(i64, i32)> = None;
+        for purchase in purchases {
+            match window.as_mut() {
+                Some((start, used)) if purchase.bought_at < *start + BUY_LIMIT_WINDOW_SECS => {
+                    *used = used.saturating_add(purchase.quantity);
+                },
+                _ => window = Some((purchase.bought_at, purchase.quantity)),
+            }
+        }
+
+        window
+            .map(|(start, used)| (used, start + BUY_LIMIT_WINDOW_SECS))
+            .filter(|&(_, resets_at)| now < resets_at)
+    }
+
+    /// Allowance left of `limit` in the current window of `item`
+    pub fn remaining(&self, item: &str, limit: i32, now: i64) -> i32 {
+        let used = self.window(item, now).map_or(0, |(used, _)| used);
+        if used > limit {
+            warn!(desc = "Bought more than the buy limit", item, used, limit);
+        }
+        (limit - used).max(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn window_resets_after_first_purchase() {
+        let purchase = |quantity, bought_at| Purchase { item: "Gold bar".to_string(), quantity, bought_at };
+        let mut ledger = PurchaseLedger::default();
+        ledger.record(purchase(4_000, 0));
+        ledger.record(purchase(2_000, 3 * 60 * 60));
+
+        // Both are in the window started by the first purchase
+        assert_eq!(ledger.window("Gold bar", 3 * 60 * 60), Some((6_000, BUY_LIMIT_WINDOW_SECS)));
+        assert_eq!(ledger.remaining("Gold bar", 10_000, 3 * 60 * 60), 4_000);
+        assert_eq!(ledger.remaining("Ruby", 10_000, 3 * 60 * 60), 10_000);
+
+        // Reset, even though the second purchase was under 4 hours ago
+        assert_eq!(ledger.remaining("Gold bar", 10_000, BUY_LIMIT_WINDOW_SECS), 10_000);
+
+        // A purchase after the reset starts a new window
+        ledger.record(purchase(1_000, 5 * 60 * 60));
+        assert_eq!(ledger.window("Gold bar", 6 * 60 * 60), Some((1_000, 9 * 60 * 60)));
+        ledger.prune(6 * 60 * 60);
+        assert_eq!(ledger.purchases, vec![purchase(1_000, 5 * 60 * 60)]);
+    }
+}
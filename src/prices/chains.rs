@@ -0,0 +1,270 @@
+//! Recipes evaluated together with the recipes producing their inputs,
+//! deciding for each intermediate item whether making it beats buying it
+use std::collections::HashMap;
+
+use tracing::{debug, instrument};
+
+use crate::{
+    config::{Membership, PricingStrategy},
+    gp::{Gp, Rounding},
+    item_search::{recipe_graph::RecipeGraph, recipes::{Recipe, RecipeTime}},
+    types::SECOND_PER_TICK,
+};
+
+use super::prices::PriceHandle;
+
+/// Times a chain is re-evaluated at its improved GP/h before settling on its decisions
+const MAX_ITERATIONS: usize = 20;
+
+/// Making an intermediate item with one of the recipes producing it
+#[derive(Debug, Clone)]
+pub struct MakeOption {
+    pub recipe: String,
+    /// Per item, including any of its own intermediates that are made
+    pub unit_cost: Gp,
+    pub unit_time_sec: f64,
+}
+
+/// An intermediate item needed for one chained recipe
+#[derive(Debug, Clone)]
+pub struct ChainStep {
+    pub item: String,
+    /// 0 for inputs of the chained recipe itself
+    pub depth: usize,
+    pub quantity: f64,
+    /// `None` if the item has no price
+    pub buy_price: Option<Gp>,
+    /// Best producer of the item. `None` if none of them can be priced
+    pub make: Option<MakeOption>,
+    pub made: bool,
+}
+
+/// Cost, revenue and time of one recipe, along with everything made for it
+#[derive(Debug, Clone, Copy)]
+pub struct ChainTotals {
+    pub cost: Gp,
+    /// After tax
+    pub revenue: Gp,
+    pub time_sec: f64,
+}
+
+impl ChainTotals {
+    pub fn profit(&self) -> Gp {
+        self.revenue - self.cost
+    }
+
+    pub fn gp_per_hour(&self) -> Gp {
+        self.profit().per_hour(self.time_sec)
+    }
+
+    /// Coins each second of the chain is worth. Never negative, as time can always be spent idle
+    fn gp_per_sec(&self) -> f64 {
+        if self.time_sec > 0.0 {
+            (self.profit().to_f64() / self.time_sec).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecipeChain {
+    pub name: String,
+    /// Buying every input. `None` if an intermediate item has no price
+    pub buying: Option<ChainTotals>,
+    /// Making the intermediates in `steps` marked as made
+    pub chained: ChainTotals,
+    /// Depth first, in the order the items are needed
+    pub steps: Vec<ChainStep>,
+}
+
+impl RecipeChain {
+    pub fn made_items(&self) -> impl Iterator<Item = &String> {
+        self.steps.iter().filter(|step| step.made).map(|step| &step.item)
+    }
+}
+
+/// Every recipe in `names`, or all recipes when empty, with an input another recipe produces.
+/// An intermediate item is made when its cost, plus the time making it takes at the chain's GP/h,
+/// is below its buy price. The GP/h is re-estimated from those decisions until they settle.
+/// Recipes with items paid for once per session are left out, both as chains and as producers,
+/// as their one-off cost has no per-item share.
+/// Ordered by chained GP/h, highest first
+#[instrument(level = "debug", skip(price_handle, membership))]
+pub fn evaluate_chains(price_handle: &PriceHandle, names: &[String], membership: &Membership) -> Vec<RecipeChain> {
+    let graph = RecipeGraph::new(&price_handle.recipe_list);
+
+    let mut chains: Vec<RecipeChain> = price_handle.recipe_list.recipes.values()
+        .filter(|recipe| !membership.excludes(recipe.members))
+        .filter(|recipe| names.is_empty() || names.contains(&recipe.name))
+        .filter(|recipe| recipe.inputs.pay_once.is_none())
+        .filter(|recipe| !graph.intermediates(recipe).is_empty())
+        .filter_map(|recipe| evaluate_chain(price_handle, &graph, membership, recipe))
+        .collect();
+
+    chains.sort_by(|a, b| b.chained.gp_per_hour().cmp(&a.chained.gp_per_hour()).then_with(|| a.name.cmp(&b.name)));
+    debug!(desc = "Evaluated recipe chains", count = chains.len());
+
+    chains
+}
+
+fn evaluate_chain(price_handle: &PriceHandle, graph: &RecipeGraph, membership: &Membership, recipe: &Recipe) -> Option<RecipeChain> {
+    let evaluator = |make, time_value| ChainEvaluator { price_handle, graph, membership, time_value, make };
+
+    let buying = evaluator(false, 0.0).evaluate(recipe).map(|(totals, _)| totals);
+    let mut time_value = buying.map_or(0.0, |totals| totals.gp_per_sec());
+    let (mut chained, mut steps) = evaluator(true, time_value).evaluate(recipe)?;
+
+    // Time is only worth what the chain earns with it, which depends on what is made
+    for _ in 1..MAX_ITERATIONS {
+        let next_value = chained.gp_per_sec();
+        if (next_value - time_value).abs() < 1e-9 {
+            break;
+        }
+        time_value = next_value;
+        (chained, steps) = evaluator(true, time_value).evaluate(recipe)?;
+    }
+
+    Some(RecipeChain { name: recipe.name.clone(), buying, chained, steps })
+}
+
+/// Seconds to make `recipe` once, preferring its ticks over `number_per_hour`
+fn craft_time_sec(recipe: &Recipe) -> Option<f64> {
+    match recipe.ticks {
+        RecipeTime::Time(ticks) => Some(f64::from(ticks * SECOND_PER_TICK)),
+        RecipeTime::INVALID => recipe.number_per_hour
+            .filter(|number| *number > 0)
+            .map(|number| 3600.0 / f64::from(number)),
+    }
+}
+
+/// Coins and time spent getting some quantity of an item, along with the intermediates behind it
+#[derive(Debug, Default)]
+struct Acquired {
+    cost: f64,
+    time_sec: f64,
+    steps: Vec<ChainStep>,
+}
+
+struct ChainEvaluator<'a> {
+    price_handle: &'a PriceHandle,
+    graph: &'a RecipeGraph,
+    membership: &'a Membership,
+    /// Coins each second spent making an item is worth
+    time_value: f64,
+    /// False to buy every input
+    make: bool,
+}
+
+impl ChainEvaluator<'_> {
+    fn evaluate(&self, recipe: &Recipe) -> Option<(ChainTotals, Vec<ChainStep>)> {
+        let pricing = self.price_handle.recipe_pricing(recipe);
+        let output_items = self.price_handle.parse_item_list(&recipe.expected_outputs(), false, pricing)?;
+        let output_details = PriceHandle::item_list_prices_unchecked(output_items, false, pricing);
+        let revenue = self.price_handle.total_details_price(&output_details, true);
+
+        let mut path = vec![recipe.name.clone()];
+        let inputs = self.acquire_inputs(recipe, 1.0, 0, &mut path)?;
+
+        let totals = ChainTotals {
+            cost: Gp::from_f64(inputs.cost, Rounding::Up),
+            revenue,
+            time_sec: craft_time_sec(recipe)? + inputs.time_sec,
+        };
+        Some((totals, inputs.steps))
+    }
+
+    /// Every input of `recipe`, for making it `number` times.
+    /// `path` holds the recipes already being made, so cycles are never followed
+    fn acquire_inputs(&self, recipe: &Recipe, number: f64, depth: usize, path: &mut Vec<String>) -> Option<Acquired> {
+        let pricing = self.price_handle.recipe_pricing(recipe);
+        let mut inputs: Vec<_> = recipe.inputs.inputs.iter().collect();
+        inputs.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut total = Acquired::default();
+        for (item_name, &quantity) in inputs {
+            let acquired = self.acquire(item_name, f64::from(quantity) * number, depth, pricing, path)?;
+            total.cost += acquired.cost;
+            total.time_sec += acquired.time_sec;
+            total.steps.extend(acquired.steps);
+        }
+
+        Some(total)
+    }
+
+    fn acquire(&self, item_name: &str, quantity: f64, depth: usize, pricing: PricingStrategy, path: &mut Vec<String>) -> Option<Acquired> {
+        let buy_price = self.buy_price(item_name, pricing);
+        let buy_cost = buy_price.map(|price| price.to_f64() * quantity);
+        if !self.graph.is_intermediate(item_name) {
+            return Some(Acquired { cost: buy_cost?, ..Acquired::default() });
+        }
+
+        let best = if self.make { self.best_producer(item_name, quantity, depth, path) } else { None };
+        let making_wins = match (&best, buy_cost) {
+            (Some((_, made)), Some(buy_cost)) => self.score(made) < buy_cost,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        let step = ChainStep {
+            item: item_name.to_string(),
+            depth,
+            quantity,
+            buy_price,
+            make: best.as_ref().map(|(recipe, made)| MakeOption {
+                recipe: recipe.clone(),
+                unit_cost: Gp::from_f64(made.cost / quantity, Rounding::Up),
+                unit_time_sec: made.time_sec / quantity,
+            }),
+            made: making_wins,
+        };
+
+        match best {
+            Some((_, made)) if step.made => {
+                let steps = std::iter::once(step).chain(made.steps).collect();
+                Some(Acquired { steps, ..made })
+            },
+            _ => Some(Acquired { cost: buy_cost?, time_sec: 0.0, steps: vec![step] }),
+        }
+    }
+
+    /// Producer of `quantity` of `item_name` with the lowest cost and time.
+    /// Each producer's own intermediates are bought or made in the same way
+    fn best_producer(&self, item_name: &str, quantity: f64, depth: usize, path: &mut Vec<String>) -> Option<(String, Acquired)> {
+        let mut best: Option<(String, Acquired)> = None;
+
+        for (recipe_name, made_per_recipe) in self.graph.producers(item_name) {
+            let Some(recipe) = self.price_handle.recipe_list.recipes.get(recipe_name) else { continue };
+            if path.contains(recipe_name) || self.membership.excludes(recipe.members) || recipe.inputs.pay_once.is_some() {
+                continue;
+            }
+            let Some(time_sec) = craft_time_sec(recipe) else { continue };
+
+            let number = quantity / f64::from(*made_per_recipe);
+            path.push(recipe_name.clone());
+            let inputs = self.acquire_inputs(recipe, number, depth + 1, path);
+            path.pop();
+
+            let Some(mut made) = inputs else { continue };
+            made.time_sec += time_sec * number;
+            if best.as_ref().is_none_or(|(_, best)| self.score(&made) < self.score(best)) {
+                best = Some((recipe_name.clone(), made));
+            }
+        }
+
+        best
+    }
+
+    /// Cost, with the time taken valued at `time_value`
+    fn score(&self, acquired: &Acquired) -> f64 {
+        acquired.cost + self.time_value * acquired.time_sec
+    }
+
+    /// `None` if the item is missing or excluded for a stale price.
+    /// See [`PriceHandle::parse_item_list`]
+    fn buy_price(&self, item_name: &str, pricing: PricingStrategy) -> Option<Gp> {
+        let item_list = HashMap::from([(item_name.to_string(), 1.0)]);
+        let (item, _) = self.price_handle.parse_item_list(&item_list, true, pricing)?.pop()?;
+        item.strategy_price(true, pricing)
+    }
+}
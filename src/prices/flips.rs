@@ -0,0 +1,87 @@
+//! Margin flipping: buying items at their low price and selling at their high price
+use tracing::{debug, instrument};
+
+use crate::{
+    config::{FlipOptions, Membership},
+    gp::Gp,
+    item_search::{data_types::latest::SPECIAL_ITEM_NAMES, item_search::HourlyVolume},
+};
+
+use super::prices::PriceHandle;
+
+/// A single item bought and sold back up to its buy limit
+#[derive(Debug, Clone)]
+pub struct Flip {
+    pub name: String,
+    pub buy: Gp,
+    pub sell: Gp,
+    /// Per item, after tax on the sale
+    pub margin: Gp,
+    /// Remaining buy limit. See [`PriceHandle::remaining_limit`]
+    pub limit: i32,
+    /// Traded on both sides
+    pub hourly_volume: Option<f64>,
+    /// Seconds since the older of the high and low trades
+    pub price_age: Option<i64>,
+}
+
+impl Flip {
+    /// Profit of flipping the whole buy limit
+    pub fn profit(&self) -> Gp {
+        self.margin.times(i64::from(self.limit))
+    }
+
+    /// Coins needed to buy the whole buy limit
+    pub fn capital(&self) -> Gp {
+        self.buy.times(i64::from(self.limit))
+    }
+
+    /// Percentage return on each item bought
+    pub fn roi(&self) -> f64 {
+        if self.buy == Gp::ZERO {
+            return 0.0;
+        }
+        100.0 * self.margin.to_f64() / self.buy.to_f64()
+    }
+}
+
+/// Every tradeable item with a taxed profit, ranked by profit over its buy limit.
+/// Items without a buy limit, untradeable with `membership`, or outside the `options` filters, are skipped
+#[instrument(level = "debug", skip(price_handle))]
+pub fn scan(price_handle: &PriceHandle, options: &FlipOptions, membership: &Membership) -> Vec<Flip> {
+    let max_age = i64::from(options.max_price_age_mins) * 60;
+
+    let mut flips: Vec<Flip> = price_handle.all_items.items.values()
+        .filter(|item| !SPECIAL_ITEM_NAMES.contains(&item.name.as_str()))
+        .filter(|item| !membership.excludes_item(item.members))
+        .filter_map(|item| {
+            let buy = Gp::from(item.price(false)?);
+            let sell = Gp::from(item.price(true)?);
+            let margin = price_handle.tax.after_tax(&item.name, sell, 1.0) - buy;
+            let limit = price_handle.remaining_limit(item)?;
+            if margin <= Gp::ZERO || limit <= 0 {
+                return None;
+            }
+
+            let price_age = price_handle.price_age(item, true).max(price_handle.price_age(item, false));
+            if price_age.is_some_and(|age| age > max_age) {
+                return None;
+            }
+
+            // Buying and selling back both need trades, so the thinner side must be busy enough
+            let volume = price_handle.all_items.hourly_volumes.get(&item.name);
+            if let Some(min_volume) = options.min_hourly_volume
+                && volume.is_none_or(|volume| volume.thinnest() < min_volume) {
+                return None;
+            }
+
+            let hourly_volume = volume.map(HourlyVolume::total);
+            Some(Flip { name: item.name.clone(), buy, sell, margin, limit, hourly_volume, price_age })
+        })
+        .collect();
+
+    flips.sort_by(|a, b| b.profit().cmp(&a.profit()).then_with(|| a.name.cmp(&b.name)));
+    debug!(desc = "Found flips", count = flips.len());
+
+    flips
+}
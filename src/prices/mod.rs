@@ -1,3 +1,10 @@
+pub mod allocation;
+pub mod backtest;
+pub mod buy_limits;
+pub mod chains;
+pub mod flips;
 pub mod pareto_sort;
 #[allow(clippy::module_inception)]
 pub mod prices;
+pub mod simulation;
+pub mod tax;
@@ -96,17 +96,17 @@ pub mod custom_types {
 
     use itertools::Itertools;
 
-    use crate::types::OverviewRow;
+    use crate::{gp::Gp, types::OverviewRow};
 
     pub type Weights = super::Weights;
 
     fn lin_scalarization(x: &OverviewRow, weights: &Weights) -> f32 {
-        #[allow(clippy::cast_precision_loss)]
+        #[allow(clippy::cast_possible_truncation)]
         [
-            x.profit as f32,
-            x.total_gp() as f32,
+            x.profit.to_f64() as f32,
+            x.total_gp().to_f64() as f32,
             x.total_time().unwrap_or(0.),
-            x.gph() as f32,
+            x.gph().to_f64() as f32,
         ]
         .iter()
         .zip(weights.iter())
@@ -151,14 +151,14 @@ pub mod custom_types {
     }
 
     // TODO: What is the reasoning for this?
-    pub fn compute_weights(coins: i32, config_weights: &crate::config::Weights) -> Weights {
+    pub fn compute_weights(coins: Gp, config_weights: &crate::config::Weights) -> Weights {
         // let [margin, time, gp_h] = config_weights;
         let margin = config_weights.margin;
         let time = config_weights.time;
         let gp_h = config_weights.gph;
 
-        #[allow(clippy::cast_precision_loss)]
-        let money_to_time = (margin, 10.0 / (coins as f32));
+        #[allow(clippy::cast_possible_truncation)]
+        let money_to_time = (margin, 10.0 / (coins.to_f64() as f32));
         let factor = money_to_time.0 + money_to_time.1;
 
         [
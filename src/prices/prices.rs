@@ -1,21 +1,24 @@
 use crate::{
-    config::{Membership, OverviewFilter, OverviewSortBy},
+    config::{OverviewFilter, OverviewSortBy, PricingStrategy, StaleAction, StalePrices, TimeStep},
+    gp::{Gp, Rounding},
     helpers::f_round,
     item_search::{
         item_search::{Item, ItemSearch},
         recipes::{Recipe, RecipeBook, RecipeTime},
     },
-    types::{DetailedTable, TableInputs, OverviewRow, SEC_IN_HOUR},
+    types::{DetailedTable, ItemSensitivity, LimitingFactor, PriceHistory, PriceSensitivity, TableInputs, OverviewRow, SEC_IN_HOUR},
 };
 
 use std::collections::HashMap;
 
 use super::pareto_sort::custom_types::{optimal_sort, Weights};
+use super::buy_limits::PurchaseLedger;
+use super::tax::TaxRules;
 
 use tracing::{debug, warn};
 
 // TODO: CHANGE TO ACTUAL TYPES
-pub type Row = (i32, i32, i32, RecipeTime);
+pub type Row = (Gp, Gp, Gp, RecipeTime);
 
 // TODO: Remove when changing types
 // type _TableRow = (String, String, String, String, String);
@@ -27,90 +30,115 @@ pub type Table = Vec<_TableRow>; // Vec of parsed Rows
 pub struct PriceHandle {
     pub all_items: ItemSearch,
     pub recipe_list: RecipeBook,
-    pub coins: i32,
+    pub coins: Gp,
     pub pmargin: f32,
+    pub stale_prices: StalePrices,
+    pub tax: TaxRules,
+    /// Used for recipes without their own `pricing`
+    pub pricing: PricingStrategy,
+    /// Purchases already counted against buy limits, as of `ledger_time`
+    pub buy_ledger: PurchaseLedger,
+    pub ledger_time: i64,
+    /// Hours played. Recipes are made for at most this long
+    pub session_hours: f32,
+    /// Share of each item's traded volume our offers can fill. Uncapped when `None`
+    pub max_volume_fraction: Option<f32>,
 }
 
 use crate::types::SECOND_PER_TICK;
 
-// NOTE: Copy implemented since only simple enum variants
-#[derive(Debug, Default, Clone, Copy)]
-pub enum TimeType {
-    SingleHour,
-    #[default]
-    MaxHours,
-}
-
+/// Length of a session unless set. The logout timer
+pub const DEFAULT_SESSION_HOURS: f32 = 6.0;
 
+/// Number of recipes made in a session of `hours`.
+/// Limited by `number_per_hour` over the session, and by the `coins` to buy inputs.
 /// # Panics
 /// Panics if `single_cost` is negative. But this shouldn't be implemented in practice
-pub fn update_recipe_number(number_per_hour: Option<i32>, coins: i32, single_cost: i32, time_type: TimeType) -> i32 {
-    // assert!( number_per_hour.is_some() || single_cost != 0, "{number_per_hour:?} {single_cost}");
-    assert!(single_cost >= 0, "Cost of recipe is negative?");
-
-    let no_cost: bool = single_cost == 0;
-
-    if number_per_hour.is_none() && no_cost {
-        // No per-hour info and no cost --> return a safe default of 1.
-        return 1;
-    }
-
-
-    let effective_max_from_coins = (single_cost > 0).then(||
-        coins.saturating_div(single_cost)
-    );
+pub fn update_recipe_number(number_per_hour: Option<i32>, coins: Gp, single_cost: Gp, hours: f32) -> i32 {
+    assert!(single_cost >= Gp::ZERO, "Cost of recipe is negative?");
 
-    let val_per_hour = number_per_hour.unwrap_or_else(||
-        effective_max_from_coins
-            .expect("no-cost should be handled already in the function")
-    );
+    // Recipe numbers are i32, so a large bankroll is capped rather than truncated
+    let by_coins = coins.affordable(single_cost)
+        .map(|num| i32::try_from(num).unwrap_or(i32::MAX));
 
-
-    if matches!(time_type, TimeType::SingleHour) || no_cost || number_per_hour.is_none() {
-        return effective_max_from_coins.map_or(val_per_hour,
-            |num| val_per_hour.min(num)
-        )
-    }
-
-    let number_per_hour = val_per_hour;
-
-    // Calculate the highest number of hours as a multiple of number_per_hour
-    let cost_per_hour_i64: i64 = i64::from(single_cost).saturating_mul(i64::from(number_per_hour));
-    let coins_i64 = i64::from(coins);
-    let single_cost_i64 = i64::from(single_cost);
-
-    let full_hours = coins_i64.saturating_div(cost_per_hour_i64);
     #[allow(clippy::cast_possible_truncation)]
-    let mut total_number = (full_hours as i32).saturating_mul(number_per_hour);
+    // SAFETY: float to int casts saturate
+    let by_session = number_per_hour
+        .map(|number| (f64::from(number) * f64::from(hours)).floor() as i32);
 
-    // Calculate any fractional time lost through integer truncation
-    let remainder_time = coins_i64.saturating_sub(full_hours.saturating_mul(cost_per_hour_i64));
-    if remainder_time >= single_cost_i64 {
-        #[allow(clippy::cast_possible_truncation)]
-        let extra_time = remainder_time.saturating_div(single_cost_i64) as i32;
-        total_number = total_number.saturating_add(extra_time);
+    match (by_session, by_coins) {
+        // No per-hour info and no cost --> return a safe default of 1.
+        (None, None) => 1,
+        (Some(number), None) | (None, Some(number)) => number,
+        (Some(session), Some(coins)) => session.min(coins),
     }
-
-    // Know `no_cost` must be false at this point
-    // let effective_max_from_coins: i32 = coins.saturating_div(single_cost);
-    // let effective_max_from_coins: i32 = effective_max_from_coins.
-
-    total_number.min(
-        effective_max_from_coins.expect("`no_cost` must be false at this point")
-    )
     .max(1)
 }
 
 impl PriceHandle {
-    pub fn new(all_items: ItemSearch, recipe_list: RecipeBook, coins: i32, pmargin: f32) -> Self {
+    pub fn new(all_items: ItemSearch, recipe_list: RecipeBook, coins: Gp, pmargin: f32, stale_prices: StalePrices, tax: TaxRules, pricing: PricingStrategy) -> Self {
         Self {
             all_items,
             recipe_list,
             coins,
             pmargin,
+            stale_prices,
+            tax,
+            pricing,
+            buy_ledger: PurchaseLedger::default(),
+            ledger_time: 0,
+            session_hours: DEFAULT_SESSION_HOURS,
+            max_volume_fraction: None,
         }
     }
 
+    pub fn set_session_hours(&mut self, hours: f32) {
+        self.session_hours = hours;
+    }
+
+    /// See [`crate::config::Liquidity`]
+    pub fn set_max_volume_fraction(&mut self, max_fraction: Option<f32>) {
+        self.max_volume_fraction = max_fraction;
+    }
+
+    /// Item slowest to trade for a single recipe, and seconds for its offers to fill.
+    /// Offers fill at `max_volume_fraction` of the item's hourly volume.
+    /// `None` when uncapped, or no item has a known volume
+    fn slowest_fill<'a, I: IntoIterator<Item = (&'a Item, &'a f32)>>(&self, items: I) -> Option<(String, f64)> {
+        let max_fraction = f64::from(self.max_volume_fraction?);
+
+        items.into_iter()
+            .filter_map(|(item, quantity)| {
+                let filled_per_hour = max_fraction * self.all_items.hourly_volumes.get(&item.name)?.total();
+                Some((item.name.clone(), f64::from(*quantity) * 3600.0 / filled_per_hour))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Buy limits use the allowance left in each item's current window at `now`
+    pub fn set_buy_ledger(&mut self, ledger: PurchaseLedger, now: i64) {
+        self.buy_ledger = ledger;
+        self.ledger_time = now;
+    }
+
+    /// Buy limit of `item` left in its current window. `None` if it has no limit
+    pub fn remaining_limit(&self, item: &Item) -> Option<i32> {
+        item.limit.map(|limit| self.buy_ledger.remaining(&item.name, limit, self.ledger_time))
+    }
+
+    /// Pricing strategy of `recipe`, or the default of this run
+    pub fn recipe_pricing(&self, recipe: &Recipe) -> PricingStrategy {
+        recipe.pricing.unwrap_or(self.pricing)
+    }
+
+    /// Resolve all item prices from the stored snapshot at or before `timestamp`.
+    /// See [`ItemSearch::set_prices_as_of`]
+    /// # Errors
+    /// Errors if the price history can not be read.
+    pub fn set_prices_as_of(&mut self, timestamp: i64) -> Result<Option<i64>, std::io::Error> {
+        self.all_items.set_prices_as_of(timestamp)
+    }
+
     // TODO: Change price_options to a struct; like FileOptions?
     /// Display recipe overview for every recipe recorded in memory
     /// # Panics
@@ -131,12 +159,10 @@ impl PriceHandle {
         let recipe_list = self.recipe_list.get_all_recipes();
         assert!(!recipe_list.is_empty());
 
-        let time_type = price_options.time_type;
-
         let all_recipe_prices = recipe_list
             .keys()
             .filter_map(|recipe_name| {
-                let overview_output = self.recipe_price_overview_from_string(recipe_name, time_type)?;
+                let overview_output = self.recipe_price_overview_from_string(recipe_name)?;
                 Some((recipe_name, overview_output))
             })
             .collect::<HashMap<_, _>>();
@@ -150,13 +176,7 @@ impl PriceHandle {
         for (recipe_name, (mut overview, (cost, _revenue))) in all_recipe_prices {
             let needs_members = recipe_list[recipe_name].members;
 
-            let skip = match membership_option {
-                Membership::F2P => needs_members,
-                Membership::P2P => !needs_members,
-                Membership::BOTH => false,
-            };
-
-            if skip {
+            if membership_option.excludes(needs_members) {
                 debug!(
                     desc = "Skipping recipe for membership requirement...",
                     name = %recipe_name,
@@ -171,7 +191,7 @@ impl PriceHandle {
             let recipe_cost = cost;
 
             let cant_afford = coins < recipe_cost;
-            let no_profit = profit <= 0;
+            let no_profit = profit <= Gp::ZERO;
 
             // TODO: Assign some boolean values to variables so reused?
             // Or leave to the compiler instead?
@@ -230,25 +250,33 @@ impl PriceHandle {
             OverviewSortBy::Custom => optimal_sort(&mut all_overviews, sort_by_weights, !reverse),
         }
 
+        // Keep the chosen order, but list recipes priced from stale trades last
+        // Stale recipes are only left at this point when flagged. See `parse_item_list`
+        all_overviews.sort_by_key(|k| k.stale);
+
         all_overviews
     }
 
-    pub fn recipe_lookup_from_recipe(&self, recipe: &Recipe, time_type: TimeType) -> Option<DetailedTable> {
+    pub fn recipe_lookup_from_recipe(&self, recipe: &Recipe) -> Option<DetailedTable> {
         // Need to parse item strings into Item objects
         // debug!(desc = "Parsing recipe lookup", name = &recipe.name);
-        let pay_once_items: Option<Vec<_>> = recipe.inputs.pay_once.as_ref()
-            .and_then(|items| self.parse_item_list(items));
-        let input_items = self.parse_item_list(&recipe.inputs.inputs)?;
-        let output_items = self.parse_item_list(&recipe.outputs)?;
+        let pricing = self.recipe_pricing(recipe);
+        // Items paid for once that can't be priced exclude the recipe, like its inputs
+        let pay_once_items: Option<Vec<_>> = match &recipe.inputs.pay_once {
+            Some(items) => Some(self.parse_item_list(items, true, pricing)?),
+            None => None,
+        };
+        let input_items = self.parse_item_list(&recipe.inputs.inputs, true, pricing)?;
+        let output_items = self.parse_item_list(&recipe.expected_outputs(), false, pricing)?;
 
         // HashMap[item -> (price, quantity)]
         // Base price
-        let pay_once_details = pay_once_items.map(|items| PriceHandle::item_list_prices_unchecked(items, true));
-        let input_details = PriceHandle::item_list_prices_unchecked(input_items, true);
+        let pay_once_details = pay_once_items.map(|items| PriceHandle::item_list_prices_unchecked(items, true, pricing));
+        let input_details = PriceHandle::item_list_prices_unchecked(input_items, true, pricing);
 
-        let output_details = PriceHandle::item_list_prices_unchecked(output_items, false);
+        let output_details = PriceHandle::item_list_prices_unchecked(output_items, false, pricing);
 
-        let (overview, (_,_)) = self.recipe_price_overview_from_recipe(recipe, time_type)?;
+        let (overview, (_,_)) = self.recipe_price_overview_from_recipe(recipe)?;
 
         // Form table
         // Transform input/outputs to DetailedTable type
@@ -272,23 +300,95 @@ impl PriceHandle {
             .map(|(item, (price, quantity))| (item.name,price,quantity))
             .collect();
 
-        let recipe_lookup: DetailedTable = DetailedTable::new(
+        let mut recipe_lookup: DetailedTable = DetailedTable::new(
             overview,
             table_inputs,
             output_vec, // Not taxed
             self.pmargin,
         );
+        recipe_lookup.sensitivity = Some(self.price_sensitivity(&recipe_lookup));
 
         Some(recipe_lookup)
     }
 
-    pub fn recipe_price_overview_from_string(&self, recipe_name: &String, time_type: TimeType) -> Option<(OverviewRow, (i32, i32))>  {
+    /// Break-even price of every input and output line, and the profit lost per 1% price move.
+    /// Items paid for once are left out.
+    /// Outputs are taxed per unit. See [`PriceHandle::total_details_price`]
+    pub fn price_sensitivity(&self, table: &DetailedTable) -> PriceSensitivity {
+        let profit = table.overview.profit;
+
+        let inputs = table.inputs.inputs.iter().map(|(name, price, quantity)| {
+            let quantity_f64 = f64::from(*quantity);
+            // Rising by the whole profit, shared across each item
+            let break_even = Gp::from_f64(price.to_f64() + profit.to_f64() / quantity_f64, Rounding::Down);
+            ItemSensitivity {
+                name: name.clone(),
+                input: true,
+                price: *price,
+                quantity: *quantity,
+                break_even: (break_even >= Gp::ZERO).then_some(break_even),
+                per_percent: -price.mul_f64(quantity_f64 / 100.0, Rounding::Up),
+            }
+        });
+
+        let outputs = table.outputs.iter().map(|(name, price, quantity)| {
+            let quantity_f64 = f64::from(*quantity);
+            let unit_net = *price - self.tax.unit_tax(name, *price);
+            // Receiving the whole profit less, after tax
+            let net = Gp::from_f64(unit_net.to_f64() - profit.to_f64() / quantity_f64, Rounding::Up);
+            let lowered = price.mul_f64(0.99, Rounding::Down);
+            ItemSensitivity {
+                name: name.clone(),
+                input: false,
+                price: *price,
+                quantity: *quantity,
+                break_even: (net > Gp::ZERO).then(|| self.tax.price_for_net(name, net)),
+                per_percent: self.tax.after_tax(name, lowered, *quantity) - self.tax.after_tax(name, *price, *quantity),
+            }
+        });
+
+        let mut items: Vec<ItemSensitivity> = inputs.chain(outputs).collect();
+        items.sort_by(|a, b| b.input.cmp(&a.input).then_with(|| a.name.cmp(&b.name)));
+
+        PriceSensitivity { number: table.overview.number, items }
+    }
+
+    /// Attach the recent price history of every input and output item to each table.
+    /// Each item is only requested once across all tables.
+    pub fn add_price_history(&self, tables: &mut [DetailedTable], timestep: TimeStep) {
+        let mut timeseries_cache = HashMap::new();
+
+        for table in tables {
+            let pay_once = table.inputs.pay_once.iter().flatten();
+            // Inputs are bought at the high price, outputs sold at the low price
+            let offers = pay_once
+                .chain(&table.inputs.inputs)
+                .map(|detail| (detail, true))
+                .chain(table.outputs.iter().map(|detail| (detail, false)));
+
+            let items = offers
+                .filter_map(|((name, price, _), high_price)| {
+                    let timeseries = timeseries_cache
+                        .entry(name.clone())
+                        .or_insert_with(|| self.all_items.get_item_timeseries(name, timestep));
+                    let summary = timeseries.as_ref()?.summary(high_price)?;
+
+                    Some((name.clone(), *price, summary))
+                })
+                .collect();
+
+            table.price_history = Some(PriceHistory { timestep, items });
+        }
+    }
+
+    pub fn recipe_price_overview_from_string(&self, recipe_name: &String) -> Option<(OverviewRow, (Gp, Gp))>  {
         let recipe = self.recipe_list.get_recipe(recipe_name)?;
-        self.recipe_price_overview_from_recipe(recipe, time_type)
+        self.recipe_price_overview_from_recipe(recipe)
     }
 
     
-    fn calculate_buy_limit_item(input_details: &HashMap<Item, (i32, f32)>)
+    /// Input item allowing the fewest recipes within its remaining buy limit, and that number
+    fn calculate_buy_limit_item(&self, input_details: &HashMap<Item, (Gp, f32)>)
         -> Option<(Item, i32)> {
         if input_details.is_empty() {
             return None // No input items required
@@ -297,7 +397,7 @@ impl PriceHandle {
         let mut limit_item_number: Option<(Item, i32)> = None;
 
         for (item, (_, quantity)) in input_details {
-            let buy_limit = item.limit.unwrap_or(i32::MAX);
+            let buy_limit = self.remaining_limit(item).unwrap_or(i32::MAX);
 
             #[allow(clippy::cast_possible_truncation)]
             let number = 
@@ -320,46 +420,124 @@ impl PriceHandle {
     }
 
 
+    /// Number of `recipe` made in a session and what limited it.
+    /// Starts from [`update_recipe_number`], then caps it by the input with the smallest buy limit,
+    /// the slowest offer to fill and the session length
+    fn limit_recipe_number(
+        &self,
+        recipe: &Recipe,
+        cost: Gp,
+        effective_time_sec: Option<f64>,
+        buy_limit: Option<&(Item, i32)>,
+        slowest_fill: Option<&(String, f64)>,
+    ) -> (i32, LimitingFactor) {
+        let mut number = update_recipe_number(recipe.number_per_hour, self.coins, cost, self.session_hours);
+        // Whichever term bound the number in `update_recipe_number`
+        let mut limited_by = match (recipe.number_per_hour, self.coins.affordable(cost)) {
+            (None, None) => LimitingFactor::Default,
+            (None, Some(_)) => LimitingFactor::Coins,
+            (Some(_), Some(affordable)) if affordable <= i64::from(number) => LimitingFactor::Coins,
+            (Some(_), _) => LimitingFactor::NumberPerHour,
+        };
+
+        if let Some((item, limit_number)) = buy_limit
+            && *limit_number < number {
+                debug!(
+                    recipe = &recipe.name,
+                    item_limiter = &item.name,
+                    limit = &limit_number
+                );
+
+                // Update number since restricted by buy limit
+                number = *limit_number;
+                limited_by = LimitingFactor::BuyLimit(item.name.clone());
+        }
+
+        if let Some((item_name, fill_sec)) = slowest_fill {
+            // Allowed number of recipes so all offers fill within the session
+            #[allow(clippy::cast_possible_truncation)]
+            let max_allowed = ((f64::from(self.session_hours) * 3600.0) / fill_sec).floor() as i32;
+            if number > max_allowed {
+                debug!(
+                    desc = "Capping recipe number to the traded volume",
+                    recipe = %recipe.name,
+                    item_limiter = %item_name,
+                    capped_to = max_allowed
+                );
+                number = max_allowed;
+                limited_by = LimitingFactor::Liquidity(item_name.clone());
+            }
+        }
+
+        if let Some(eff_time_sec) = effective_time_sec {
+            // Allowed number of recipes so total_time <= session_hours
+            #[allow(clippy::cast_possible_truncation)]
+            let max_allowed = ((f64::from(self.session_hours) * 3600.0) / eff_time_sec).floor() as i32;
+            if max_allowed > 0 && number > max_allowed {
+                debug!(
+                    desc = "Capping recipe number to the session length",
+                    recipe = %recipe.name,
+                    old_number = number,
+                    capped_to = max_allowed
+                );
+                number = max_allowed;
+                limited_by = LimitingFactor::SessionLength;
+            }
+        }
+
+        (number.max(1), limited_by)
+    }
+
+
     /// Returns price overview and cost of inputs and (taxed) revenue from outputs
     #[allow(clippy::missing_panics_doc, reason = "infallible")]
-    pub fn recipe_price_overview_from_recipe(&self, recipe: &Recipe, time_type: TimeType) -> Option<(OverviewRow, (i32, i32))> {
+    pub fn recipe_price_overview_from_recipe(&self, recipe: &Recipe) -> Option<(OverviewRow, (Gp, Gp))> {
         // Need to parse item strings into Item objects
-        let pay_once_items: Option<Vec<_>> = recipe.inputs.pay_once.as_ref()
-            .and_then(|items| self.parse_item_list(items));
-        let input_items = self.parse_item_list(&recipe.inputs.inputs)?;
+        let pricing = self.recipe_pricing(recipe);
+        // Items paid for once that can't be priced exclude the recipe, like its inputs
+        let pay_once_items: Option<Vec<_>> = match &recipe.inputs.pay_once {
+            Some(items) => Some(self.parse_item_list(items, true, pricing)?),
+            None => None,
+        };
+        let input_items = self.parse_item_list(&recipe.inputs.inputs, true, pricing)?;
+
+        let output_items = self.parse_item_list(&recipe.expected_outputs(), false, pricing)?;
 
-        let output_items = self.parse_item_list(&recipe.outputs)?;
+        let buy_items = pay_once_items.iter().flatten().chain(&input_items);
+        let (input_age, input_stale) = self.oldest_price_age(buy_items, true, pricing);
+        let (output_age, output_stale) = self.oldest_price_age(&output_items, false, pricing);
+
+        let mut overridden: Vec<String> = pay_once_items.iter().flatten().chain(&input_items).chain(&output_items)
+            .map(|(item, _)| &item.name)
+            .filter(|name| self.all_items.is_overridden(name))
+            .cloned()
+            .collect();
+        overridden.sort();
+        overridden.dedup();
 
-        let pay_once_details = pay_once_items.map(|items| PriceHandle::item_list_prices_unchecked(items, true));
-        let input_details = PriceHandle::item_list_prices_unchecked(input_items, true);
+        let pay_once_details = pay_once_items.map(|items| PriceHandle::item_list_prices_unchecked(items, true, pricing));
+        let input_details = PriceHandle::item_list_prices_unchecked(input_items, true, pricing);
         // assert!(!input_details.is_empty());
 
-        let output_details = PriceHandle::item_list_prices_unchecked(output_items, false);
+        let output_details = PriceHandle::item_list_prices_unchecked(output_items, false, pricing);
         assert!(!output_details.is_empty());
 
 
         // Minimum of (max_buy_limit / item_number_in_recipe) for all inputs
-        let item_limit_number = PriceHandle::calculate_buy_limit_item(
-            &input_details
+        let item_limit_number = self.calculate_buy_limit_item(&input_details);
+        let slowest_fill = self.slowest_fill(
+            input_details.iter().chain(&output_details)
+                .map(|(item, (_, quantity))| (item, quantity))
         );
 
 
         let pay_once_cost = pay_once_details.as_ref().map(|details|
-            PriceHandle::total_details_price(
-                &details.values().copied().collect::<Vec<_>>(),
-                false
-            )
+            self.total_details_price(details, false)
         );
 
 
-        let revenue = PriceHandle::total_details_price(
-            &output_details.into_values().collect::<Vec<_>>(),
-            true
-        );
-        let cost = PriceHandle::total_details_price(
-            &input_details.into_values().collect::<Vec<_>>(),
-            false
-        );
+        let revenue = self.total_details_price(&output_details, true);
+        let cost = self.total_details_price(&input_details, false);
         let profit = revenue - cost;
 
 
@@ -397,116 +575,66 @@ impl PriceHandle {
         }
 
 
-        #[allow(clippy::cast_possible_truncation)]
-        let effective_number_per_single_hour = effective_time_sec
-            .map(|f| (3600.0 / f).ceil() as i32);
-
-        let number_per_hour = match time_type {
-            TimeType::SingleHour => effective_number_per_single_hour,
-            TimeType::MaxHours => user_number_per_hour,
-        };
-        
         // One or more of time or user_number_per_hour is set
-        let mut number = update_recipe_number(number_per_hour, self.coins, cost, time_type);
-
-        if let Some((item, limit_number)) = item_limit_number {
-            if limit_number < number {
-                debug!(
-                    recipe = &recipe.name,
-                    item_limiter = &item.name,
-                    limit = &limit_number
-                );
-
-                // Update number since restricted by buy limit
-                number = limit_number;
-            }
-        }
-
-        #[allow(clippy::items_after_statements)]
-        const MAX_HOURS: f64 = 6.0;
-
-        if let Some(eff_time_sec) = effective_time_sec {
-            // Allowed number of recipes so total_time <= MAX_HOURS
-            #[allow(clippy::cast_possible_truncation)]
-            let max_allowed = ((MAX_HOURS * 3600.0) / eff_time_sec).floor() as i32;
-            if max_allowed > 0 && number > max_allowed {
-                debug!(
-                    desc = "Capping recipe number to respect the log-out timer",
-                    recipe = %recipe.name,
-                    old_number = number,
-                    capped_to = max_allowed
-                );
-                number = max_allowed;
-            }
-        }
-        number = number.max(1);
+        let (number, limited_by) = self.limit_recipe_number(
+            recipe, cost, effective_time_sec, item_limit_number.as_ref(), slowest_fill.as_ref()
+        );
 
         #[allow(clippy::cast_possible_truncation)]
         let overview_single_time = effective_time_sec.map(|f| f as f32);
 
-        let overview = OverviewRow::new(
+        let mut overview = OverviewRow::new(
             recipe.name.clone(),
             pay_once_cost,
             profit,
             overview_single_time,
             number,
-            time_type
         );
-
-        Some((overview, (cost, revenue)))
-    }
-
-    #[allow(clippy::cast_precision_loss)]
-    pub fn apply_tax(profit: i32) -> i32 {
-        const TAX_PERCENT: f64 = 2.0;
-        const FEE_CAP: i32 = 5_000_000;
-
-        // Update to 2% tax 2025-05-29
-        // https://oldschool.runescape.wiki/w/Grand_Exchange#Convenience_fee_and_item_sink
-        if profit < 50 {
-            return profit;
-        }
-        
-
+        overview.price_age = input_age.max(output_age);
+        overview.stale = input_stale || output_stale;
+        overview.pricing = pricing;
+        overview.limited_by = limited_by;
+        overview.overridden = overridden;
         #[allow(clippy::cast_possible_truncation)]
-        // SAFETY: original values and multiplication are within i32 limit
-        let untaxed = (f64::from(profit) * TAX_PERCENT / 100.0).floor() as i32;
-        let tax: i32 = FEE_CAP.min(untaxed);
+        {
+            overview.fill_sec = slowest_fill.map(|(_, fill_sec)| fill_sec as f32);
+        }
+        overview.limit_reset = item_limit_number.as_ref().and_then(|(item, _)| {
+            let (_, resets_at) = self.buy_ledger.window(&item.name, self.ledger_time)?;
+            Some((item.name.clone(), resets_at - self.ledger_time))
+        });
 
-        profit - tax
+        Some((overview, (cost, revenue)))
     }
 
-    #[allow(clippy::cast_precision_loss)]
-    pub fn total_details_price(price_details: &[(i32, f32)], apply_tax: bool) -> i32 {
-        // total price for each item is price * quantity
-        let price_totals = price_details.iter().map(|t| f64::from(t.0) * f64::from(t.1));
-        
-        #[allow(clippy::cast_possible_truncation)]
-        return if apply_tax {
-            price_totals.map(|f| f as i32)
-                .map(PriceHandle::apply_tax)
-                .sum::<i32>()
+    /// Outputs (`apply_tax`) are received, so each item is taxed per unit and rounds down.
+    /// See [`TaxRules::after_tax`].
+    /// Inputs are paid, so the total rounds up. See [`crate::gp`]
+    pub fn total_details_price(&self, price_details: &HashMap<Item, (Gp, f32)>, apply_tax: bool) -> Gp {
+        if apply_tax {
+            price_details.iter()
+                .map(|(item, (price, quantity))| self.tax.after_tax(&item.name, *price, *quantity))
+                .sum()
         } else {
-            price_totals.sum::<f64>().floor() as i32
+            // total price for each item is price * quantity
+            let total: f64 = price_details.values()
+                .map(|(price, quantity)| price.to_f64() * f64::from(*quantity))
+                .sum();
+            Gp::from_f64(total, Rounding::Up)
         }
-
-        // // SAFETY: Max size of i32 < f64
-        // return total.floor() as i32
     }
 
     #[allow(clippy::cast_precision_loss)]
     /// Returns total time in hours, and estimated GP/hour
-    pub fn recipe_time_h(time: f32, number: i32, margin: i32, total_margin: bool) -> (f32, i32) {
+    pub fn recipe_time_h(time: f32, number: i32, margin: Gp, total_margin: bool) -> (f32, Gp) {
         let time_h: f32 = time / (60. * 60.);
         let total_time_h: f32 = f_round(number as f32 * time_h, 2);
 
-        // SAFETY: f64 can represent all values of i32
         // TODO: times should not be small enough to surpass accuracy limit?
-        #[allow(clippy::cast_possible_truncation)]
         let gp_h = if total_margin {
-            (f64::from(margin) / f64::from(total_time_h)).floor() as i32
+            margin.per_hour(f64::from(total_time_h) * 3600.0)
         } else {
-            (f64::from(margin) / f64::from(time_h)).floor() as i32
+            margin.per_hour(f64::from(time))
         };
 
         (total_time_h, gp_h)
@@ -516,11 +644,12 @@ impl PriceHandle {
     pub fn item_list_prices<I: IntoIterator<Item = (Item, f32)>>(
         item_list: I,
         price_type: bool,
-    ) -> HashMap<Item, (Option<i32>, f32)> {
+        pricing: PricingStrategy,
+    ) -> HashMap<Item, (Option<Gp>, f32)> {
         item_list
             .into_iter()
             .map(|(i, q)| {
-                let price = i.price(price_type);
+                let price = i.strategy_price(price_type, pricing);
                 (i, (price, q))
             })
             .collect()
@@ -534,17 +663,52 @@ impl PriceHandle {
     pub fn item_list_prices_unchecked<I: IntoIterator<Item = (Item, f32)>>(
         item_list: I,
         price_type: bool,
-    ) -> HashMap<Item, (i32, f32)> {
+        pricing: PricingStrategy,
+    ) -> HashMap<Item, (Gp, f32)> {
         item_list
             .into_iter()
             .map(|(i, q)| {
-                let price = i.price(price_type).unwrap();
+                let price = i.strategy_price(price_type, pricing).unwrap();
                 (i, (price, q))
             })
             .collect()
     }
 
-    pub fn parse_item_list(&self, item_list: &HashMap<String, f32>) -> Option<Vec<(Item, f32)>> {
+    /// Seconds since the last trade of `item` on the given side.
+    /// See [`Item::price_age`] and [`ItemSearch::latest_trade_time`]
+    pub fn price_age(&self, item: &Item, high_price: bool) -> Option<i64> {
+        item.price_age(high_price, self.all_items.latest_trade_time?)
+    }
+
+    pub fn is_stale(&self, item: &Item, high_price: bool) -> bool {
+        self.price_age(item, high_price)
+            .is_some_and(|age| self.stale_prices.is_stale(high_price, age))
+    }
+
+    /// Age of the oldest price `item` is traded at, and whether any of them are stale.
+    /// See [`PricingStrategy::sides`]
+    pub fn trade_price_age(&self, item: &Item, buying: bool, pricing: PricingStrategy) -> (Option<i64>, bool) {
+        pricing.sides(buying).iter()
+            .fold((None, false), |(oldest, stale), &high_price| (
+                oldest.max(self.price_age(item, high_price)),
+                stale || self.is_stale(item, high_price),
+            ))
+    }
+
+    /// Oldest price age across `items`, and whether any of them are stale
+    fn oldest_price_age<'a, I: IntoIterator<Item = &'a (Item, f32)>>(&self, items: I, buying: bool, pricing: PricingStrategy) -> (Option<i64>, bool) {
+        items.into_iter()
+            .fold((None, false), |(oldest, stale), (item, _)| {
+                let (age, item_stale) = self.trade_price_age(item, buying, pricing);
+                (oldest.max(age), stale || item_stale)
+            })
+    }
+
+    /// Items of a recipe with their quantities.
+    /// `buying` is true for inputs, and false for outputs.
+    /// `None` if any item is missing, has no price on the traded side,
+    /// or is stale when [`StaleAction::Exclude`] is set.
+    pub fn parse_item_list(&self, item_list: &HashMap<String, f32>, buying: bool, pricing: PricingStrategy) -> Option<Vec<(Item, f32)>> {
         // TODO: Compare methods of take_while (then re-iter) vs filter_map
         let filtered_items: Vec<(Item, f32)> = item_list
             .iter()
@@ -557,6 +721,20 @@ impl PriceHandle {
             .collect();
 
         if item_list.len() == filtered_items.len() {
+            // A `latest` quote can have only one side
+            if let Some((item, _)) = filtered_items.iter().find(|(item, _)| item.strategy_price(buying, pricing).is_none()) {
+                debug!(desc = "Excluding item list with a missing price", item = %item.name, buying, %pricing);
+                return None;
+            }
+
+            if self.stale_prices.action == StaleAction::Exclude
+                && let Some((item, _)) = filtered_items.iter().find(|(item, _)| self.trade_price_age(item, buying, pricing).1) {
+                    debug!(desc = "Excluding item list with a stale price",
+                        item = %item.name, age = ?self.trade_price_age(item, buying, pricing).0, buying, %pricing
+                    );
+                    return None;
+            }
+
             // SAFETY: Know all elements are in lookup and are type Item
             Some(filtered_items)
         } else {
@@ -575,3 +753,19 @@ impl PriceHandle {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recipe_number_over_session() {
+        // 100 per hour for 2.5 hours, with coins for 1,000
+        assert_eq!(update_recipe_number(Some(100), Gp::new(10_000), Gp::new(10), 2.5), 250);
+        // Coins run out first
+        assert_eq!(update_recipe_number(Some(100), Gp::new(1_000), Gp::new(10), 2.5), 100);
+        // No cost, so only the session limits it
+        assert_eq!(update_recipe_number(Some(100), Gp::new(1_000), Gp::ZERO, 0.5), 50);
+        assert_eq!(update_recipe_number(None, Gp::new(1_000), Gp::ZERO, 0.5), 1);
+    }
+}
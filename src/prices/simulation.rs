@@ -0,0 +1,172 @@
+//! Monte Carlo profit of recipes with random drops, over a session
+use fastrand::Rng;
+use tracing::{debug, instrument};
+
+use crate::{
+    config::Membership,
+    gp::{Gp, Rounding},
+    item_search::recipes::{DropQuantity, Recipe},
+};
+
+use super::prices::PriceHandle;
+
+pub const DEFAULT_SIMULATION_RUNS: u32 = 10_000;
+
+/// Spread of a recipe's total profit across simulated sessions
+#[derive(Debug, Clone)]
+pub struct ProfitDistribution {
+    pub name: String,
+    /// Recipes made each session, as in the overview
+    pub number: i32,
+    /// Total profit with every drop at its expected quantity
+    pub expected: Gp,
+    pub p5: Gp,
+    pub median: Gp,
+    pub p95: Gp,
+    /// Fraction of sessions that lost money
+    pub loss_chance: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub runs: u32,
+    pub seed: u64,
+    pub session_hours: f32,
+    /// Ordered by 5th percentile profit, highest first
+    pub distributions: Vec<ProfitDistribution>,
+}
+
+/// A drop priced at what it sells for after tax
+struct PricedDrop {
+    chance: f64,
+    quantity: DropQuantity,
+    unit_revenue: Gp,
+}
+
+/// Simulate `runs` sessions of every recipe in `names`, or of all recipes when empty
+#[instrument(level = "debug", skip(price_handle, membership))]
+pub fn simulate(price_handle: &PriceHandle, names: &[String], membership: &Membership, runs: u32, seed: u64) -> Simulation {
+    let mut rng = Rng::with_seed(seed);
+
+    let mut recipes: Vec<_> = price_handle.recipe_list
+        .get_all_recipes()
+        .into_values()
+        .filter(|recipe| !membership.excludes(recipe.members))
+        .filter(|recipe| names.is_empty() || names.contains(&recipe.name))
+        .collect();
+    // Same seed, same results
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut distributions: Vec<ProfitDistribution> = recipes.iter()
+        .filter_map(|recipe| simulate_recipe(price_handle, recipe, runs, &mut rng))
+        .collect();
+
+    distributions.sort_by(|a, b| b.p5.cmp(&a.p5).then_with(|| a.name.cmp(&b.name)));
+    debug!(desc = "Simulated recipes", count = distributions.len());
+
+    Simulation { runs, seed, session_hours: price_handle.session_hours, distributions }
+}
+
+fn simulate_recipe(price_handle: &PriceHandle, recipe: &Recipe, runs: u32, rng: &mut Rng) -> Option<ProfitDistribution> {
+    let (overview, _) = price_handle.recipe_price_overview_from_recipe(recipe)?;
+    let expected = overview.total_gp();
+
+    let pricing = price_handle.recipe_pricing(recipe);
+    let drop_quantities = recipe.drops.keys().map(|name| (name.clone(), 1.0)).collect();
+    let drop_items = price_handle.parse_item_list(&drop_quantities, false, pricing)?;
+    let drops: Vec<PricedDrop> = PriceHandle::item_list_prices_unchecked(drop_items, false, pricing)
+        .into_iter()
+        .map(|(item, (price, _))| PricedDrop {
+            chance: recipe.drops[&item.name].chance,
+            quantity: recipe.drops[&item.name].quantity,
+            unit_revenue: price - price_handle.tax.unit_tax(&item.name, price),
+        })
+        .collect();
+
+    // Each session differs from the expected profit only by how much of each drop it received
+    let mut totals: Vec<Gp> = (0..runs)
+        .map(|_| drops.iter().fold(expected, |total, drop| {
+            let expected_quantity = f64::from(overview.number) * drop.chance * drop.quantity.mean();
+            #[allow(clippy::cast_precision_loss)]
+            let extra = sample_quantity(rng, overview.number, drop) as f64 - expected_quantity;
+            total + drop.unit_revenue.mul_f64(extra, Rounding::Down)
+        }))
+        .collect();
+    totals.sort_unstable();
+
+    let losses = totals.iter().filter(|total| **total < Gp::ZERO).count();
+    #[allow(clippy::cast_precision_loss)]
+    let loss_chance = if totals.is_empty() { 0.0 } else { losses as f64 / totals.len() as f64 };
+
+    Some(ProfitDistribution {
+        name: recipe.name.clone(),
+        number: overview.number,
+        expected,
+        p5: percentile(&totals, 5.0).unwrap_or(expected),
+        median: percentile(&totals, 50.0).unwrap_or(expected),
+        p95: percentile(&totals, 95.0).unwrap_or(expected),
+        loss_chance,
+    })
+}
+
+/// Total quantity of `drop` received over `trials` recipes
+fn sample_quantity(rng: &mut Rng, trials: i32, drop: &PricedDrop) -> u64 {
+    let (min, max) = drop.quantity.bounds();
+    let hits = sample_hits(rng, trials, drop.chance);
+    if min == max {
+        hits * u64::from(min)
+    } else {
+        (0..hits).map(|_| u64::from(rng.u32(min..=max))).sum()
+    }
+}
+
+/// Number of successes in `trials` attempts of `chance` each.
+/// Skips ahead by geometrically distributed gaps, so rare drops are cheap to sample
+fn sample_hits(rng: &mut Rng, trials: i32, chance: f64) -> u64 {
+    let trials = u64::try_from(trials).unwrap_or(0);
+    if chance >= 1.0 {
+        return trials;
+    }
+    if chance <= 0.0 {
+        return 0;
+    }
+
+    // `1.0 - chance` rounds to 1 for tiny chances, which would make every attempt a hit
+    let log_miss = (-chance).ln_1p();
+    let (mut hits, mut attempt) = (0, 0_u64);
+    loop {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let misses = ((1.0 - rng.f64()).ln() / log_miss).floor() as u64;
+        attempt = attempt.saturating_add(misses.saturating_add(1));
+        if attempt > trials {
+            return hits;
+        }
+        hits += 1;
+    }
+}
+
+/// Nearest rank percentile of sorted `values`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+fn percentile(values: &[Gp], percent: f64) -> Option<Gp> {
+    let last = values.len().checked_sub(1)?;
+    values.get((percent / 100.0 * last as f64).round() as usize).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hits_average_to_chance() {
+        let mut rng = Rng::with_seed(7);
+        assert_eq!(sample_hits(&mut rng, 500, 1.0), 500);
+        assert_eq!(sample_hits(&mut rng, 500, 0.0), 0);
+
+        let hits: u64 = (0..1_000).map(|_| sample_hits(&mut rng, 1_000, 0.01)).sum();
+        // 10 per run expected
+        assert!((9_500..10_500).contains(&hits), "{hits}");
+
+        let rare: u64 = (0..1_000).map(|_| sample_hits(&mut rng, 1_000, 1e-17)).sum();
+        assert_eq!(rare, 0);
+    }
+}
@@ -0,0 +1,115 @@
+//! Grand Exchange convenience fee, defined in `lookup_data/ge_tax.yaml`
+//! <https://oldschool.runescape.wiki/w/Grand_Exchange#Convenience_fee_and_item_sink>
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::{
+    file_io::{FileIO, FileOptions, SerChoice},
+    gp::{Gp, Rounding},
+    log_match_panic,
+};
+
+/// The fee is charged on every single item sold, not on the total of a sale
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaxRules {
+    pub rate_percent: f64,
+    /// Maximum fee for a single item
+    #[serde(deserialize_with = "crate::config::deserialize_underscored_integer")]
+    pub cap: Gp,
+    /// Items sold for less than this are not taxed
+    #[serde(deserialize_with = "crate::config::deserialize_underscored_integer")]
+    pub min_price: Gp,
+    /// Item names that are never taxed
+    #[serde(default)]
+    pub exempt: HashSet<String>,
+}
+
+impl Default for TaxRules {
+    /// Rules as of the 2% update on 2025-05-29
+    fn default() -> Self {
+        Self {
+            rate_percent: 2.0,
+            cap: Gp::new(5_000_000),
+            min_price: Gp::new(50),
+            exempt: HashSet::from(["Coins".to_string()]),
+        }
+    }
+}
+
+impl TaxRules {
+    /// # Panics
+    /// Panics if the file can not be read or parsed
+    pub fn load(path: String) -> Self {
+        let mut tax_fio = FileIO::new(path, FileOptions::new(true, false, false));
+
+        log_match_panic(
+            tax_fio.read_serialized(SerChoice::YAML),
+            "Read GE tax rules from file.",
+            "Failed to load GE tax rules.",
+        )
+    }
+
+    /// Fee taken when selling a single `item_name` for `price`. Rounded down
+    pub fn unit_tax(&self, item_name: &str, price: Gp) -> Gp {
+        if price < self.min_price || self.exempt.contains(item_name) {
+            return Gp::ZERO;
+        }
+
+        price.mul_f64(self.rate_percent / 100.0, Rounding::Down).min(self.cap)
+    }
+
+    /// Received from selling `quantity` of `item_name` at `price` each, after tax
+    pub fn after_tax(&self, item_name: &str, price: Gp, quantity: f32) -> Gp {
+        let unit_price = price - self.unit_tax(item_name, price);
+        unit_price.mul_f64(f64::from(quantity), Rounding::Down)
+    }
+
+    /// Lowest price of a single `item_name` that receives at least `net` after tax
+    pub fn price_for_net(&self, item_name: &str, net: Gp) -> Gp {
+        if net <= Gp::ZERO {
+            return Gp::ZERO;
+        }
+
+        // Received per item never falls as the price rises, so binary search
+        let (mut low, mut high) = (Gp::ZERO, net.times(2) + self.cap + self.min_price);
+        while low < high {
+            let mid = Gp::new(low.coins() + (high.coins() - low.coins()) / 2);
+            if mid - self.unit_tax(item_name, mid) >= net {
+                high = mid;
+            } else {
+                low = mid + Gp::new(1);
+            }
+        }
+        low
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn taxed_per_unit() {
+        let rules = TaxRules::default();
+
+        // Below the threshold on each unit, even though the line total is above it
+        assert_eq!(rules.after_tax("Feather", Gp::new(3), 1_000.0), Gp::new(3_000));
+        assert_eq!(rules.after_tax("Coins", Gp::new(1), 111_440.0), Gp::new(111_440));
+
+        assert_eq!(rules.after_tax("Ruby ring", Gp::new(1_300), 2.0), Gp::new(2_548));
+        // Capped per item
+        assert_eq!(rules.unit_tax("Twisted bow", Gp::new(1_500_000_000)), Gp::new(5_000_000));
+    }
+
+    #[test]
+    fn price_for_net_inverts_tax() {
+        let rules = TaxRules::default();
+
+        // 1,299 - 25 tax, as the tax rounds down
+        assert_eq!(rules.price_for_net("Ruby ring", Gp::new(1_274)), Gp::new(1_299));
+        // Untaxed below the threshold
+        assert_eq!(rules.price_for_net("Ruby ring", Gp::new(49)), Gp::new(49));
+        assert_eq!(rules.price_for_net("Coins", Gp::new(1_000)), Gp::new(1_000));
+    }
+}
@@ -5,28 +5,84 @@ pub mod markdown {
     use tracing::trace;
 
     use crate::types::{
-        DetailedTable, OverviewRow, ResultsTable, RecipeDetail,
+        DetailedTable, LimitingFactor, OverviewRow, PriceHistory, PriceSensitivity, ResultsTable, RecipeDetail,
+        ALLOCATION_NUM_HEADERS, ALLOCATION_ROW_HEADERS,
+        BACKTEST_NUM_HEADERS, BACKTEST_ROW_HEADERS,
+        CHAINS_NUM_HEADERS, CHAINS_ROW_HEADERS,
+        CHAIN_STEP_NUM_HEADERS, CHAIN_STEP_ROW_HEADERS,
         DETAILED_NUM_HEADERS, DETAILED_ROW_HEADERS,
-        OVERVIEW_NUM_HEADERS, OVERVIEW_ROW_HEADERS
+        FLIPS_NUM_HEADERS, FLIPS_ROW_HEADERS,
+        HISTORY_ROW_HEADERS,
+        OVERVIEW_NUM_HEADERS, OVERVIEW_ROW_HEADERS,
+        SENSITIVITY_ROW_HEADERS,
+        SERIES_NUM_HEADERS, SERIES_ROW_HEADERS,
+        SIMULATION_NUM_HEADERS, SIMULATION_ROW_HEADERS,
     };
-    use crate::helpers::ToCommaString;
-
+    use crate::config::PricingStrategy;
+    use crate::gp::{Gp, Rounding};
+    use crate::helpers::{f_round, format_age, format_timestamp, ToCommaString};
+    use crate::prices::allocation::{Allocation, AllocationPlan};
+    use crate::prices::backtest::{BacktestSeries, SeriesStats};
+    use crate::prices::chains::{ChainStep, RecipeChain};
+    use crate::prices::flips::Flip;
+    use crate::prices::simulation::{ProfitDistribution, Simulation};
+
+    use std::collections::{BTreeSet, HashMap};
     use std::io;
 
 
 
     pub mod optimal_overview {}
 
+    /// Format a row of cells; the first column is left-aligned, the rest right-aligned
+    fn fmt_cells<S: AsRef<str>>(cells: &[S], widths: &[usize]) -> String {
+        fmt_aligned_cells(cells, widths, &[0])
+    }
+
+    /// Format a row of cells; the `left_aligned` columns are left-aligned, the rest right-aligned
+    fn fmt_aligned_cells<S: AsRef<str>>(cells: &[S], widths: &[usize], left_aligned: &[usize]) -> String {
+        let padded: Vec<String> = cells.iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| if left_aligned.contains(&i) {
+                format!("{:<width$}", cell.as_ref())
+            } else {
+                format!("{:>width$}", cell.as_ref())
+            })
+            .collect();
+
+        format!("| {} |", padded.join(" | "))
+    }
+
+    /// Markdown separator row between the header and body
+    fn fmt_separator(widths: &[usize]) -> String {
+        let separator_cells = widths.iter().map(|w| "-".repeat(*w.max(&3)));
+        format!("| {} |", separator_cells.collect::<Vec<_>>().join(" | "))
+    }
+
+    /// Widen `widths` to fit every row
+    fn update_max_widths<S: AsRef<str>>(widths: &mut [usize], rows: &[impl AsRef<[S]>]) {
+        for row in rows {
+            for (width, cell) in widths.iter_mut().zip(row.as_ref()) {
+                *width = (*width).max(cell.as_ref().len());
+            }
+        }
+    }
+
     // TODO: Is String best for large numbers of recipes?
     pub struct OptimalOverview {
         overview_rows: Vec<OverviewRow>,
         col_widths: [usize; OVERVIEW_NUM_HEADERS],
+        // Recipe name -> link to its profit chart
+        chart_links: HashMap<String, String>,
+        prices_fetched_at: Option<i64>, // Unix Timestamp
+        pricing: Option<PricingStrategy>,
     }
 
     // TODO: Name conflict with src/recipes/* ?
     pub struct DetailedRecipeLookup {
         // TODO: Store here or pass in through function arguments?
-        current_coins: i32, // User GP
+        current_coins: Gp, // User GP
 
         recipe_tables: Vec<DetailedTable>,
         current_table_idx: usize, // TODO: better way to do this?
@@ -34,6 +90,15 @@ pub mod markdown {
 
         // TODO: Vec<Option<_>>? or just keep as all entries are String::new()
         current_table_rows: Vec<[String; DETAILED_NUM_HEADERS]>, // Clear when switching tables
+        prices_fetched_at: Option<i64>, // Unix Timestamp
+    }
+
+    /// Note of when the prices used were fetched, written above the tables
+    fn write_prices_fetched_at(f: &mut impl io::Write, prices_fetched_at: Option<i64>) -> io::Result<()> {
+        if let Some(fetched_at) = prices_fetched_at {
+            writeln!(f, "Prices fetched at {}\n", format_timestamp(fetched_at))?;
+        }
+        Ok(())
     }
 
     impl Default for OptimalOverview {
@@ -41,6 +106,9 @@ pub mod markdown {
             Self {
                 overview_rows: Vec::new(), // TODO: Initialise with_capacity?
                 col_widths: [0; OVERVIEW_NUM_HEADERS],
+                chart_links: HashMap::new(),
+                prices_fetched_at: None,
+                pricing: None,
             }
         }
     }
@@ -48,13 +116,14 @@ pub mod markdown {
     impl Default for DetailedRecipeLookup {
         fn default() -> Self {
             Self {
-                current_coins: 0,
+                current_coins: Gp::ZERO,
 
                 recipe_tables: Vec::new(), // Ditto
                 current_table_idx: 0,
                 col_widths: [0; DETAILED_NUM_HEADERS],
 
                 current_table_rows: Vec::new(),
+                prices_fetched_at: None,
             }
         } 
     }
@@ -72,37 +141,11 @@ pub mod markdown {
         }
 
         fn fmt_header(&self) -> String {
-            format!(
-                "| {:<width0$} | {:>width1$} | {:>width2$} | {:>width3$} | {:>width4$} |",
-                OVERVIEW_ROW_HEADERS[0],
-                OVERVIEW_ROW_HEADERS[1],
-                OVERVIEW_ROW_HEADERS[2],
-                OVERVIEW_ROW_HEADERS[3],
-                OVERVIEW_ROW_HEADERS[4],
-                width0 = self.col_widths[0],
-                width1 = self.col_widths[1],
-                width2 = self.col_widths[2],
-                width3 = self.col_widths[3],
-                width4 = self.col_widths[4],
-            )
+            fmt_aligned_cells(&OVERVIEW_ROW_HEADERS, &self.col_widths, &Self::LEFT_ALIGNED)
         }
 
         fn fmt_item(&self, row: &Self::Row) -> String {
-            let string_cells = row.to_string_cells();
-
-            format!(
-                "| {:<width0$} | {:>width1$} | {:>width2$} | {:>width3$} | {:>width4$} |",
-                string_cells[0],
-                string_cells[1],
-                string_cells[2],
-                string_cells[3],
-                string_cells[4],
-                width0 = self.col_widths[0],
-                width1 = self.col_widths[1],
-                width2 = self.col_widths[2],
-                width3 = self.col_widths[3],
-                width4 = self.col_widths[4],
-            )
+            fmt_aligned_cells(&self.string_cells(row), &self.col_widths, &Self::LEFT_ALIGNED)
         }
 
         /// TODO: Name
@@ -112,19 +155,39 @@ pub mod markdown {
             self.update_widths();
 
             // No title 
+            write_prices_fetched_at(f, self.prices_fetched_at)?;
+            if let Some(pricing) = self.pricing {
+                writeln!(f, "Pricing: {pricing}\n")?;
+            }
 
             // Write header row
             writeln!(f, "{}", self.fmt_header())?;
 
             // Write separator row
-            let separator_cells = self.col_widths.iter().map(|w| "-".repeat(*w.max(&3)));
-            writeln!(f, "| {} |", separator_cells.collect::<Vec<_>>().join(" | "))?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
 
             // Write data rows
             for row in &self.overview_rows {
                 writeln!(f, "{}", self.fmt_item(row))?;
             }
 
+            let overridden: BTreeSet<&String> = self.overview_rows.iter().flat_map(|row| &row.overridden).collect();
+            if !overridden.is_empty() {
+                let items: Vec<&str> = overridden.into_iter().map(String::as_str).collect();
+                writeln!(f, "\nNot live prices. Overridden: {}", items.join(", "))?;
+            }
+
+            // Links to charts in the same order as the rows
+            let links: Vec<_> = self.overview_rows.iter()
+                .filter_map(|row| self.chart_links.get(&row.name).map(|link| (&row.name, link)))
+                .collect();
+            if !links.is_empty() {
+                writeln!(f, "\nProfit History Charts\n")?;
+                for (name, link) in links {
+                    writeln!(f, "- [{name}]({link})")?;
+                }
+            }
+
             Ok(())
         }
 
@@ -142,59 +205,20 @@ pub mod markdown {
 
         fn fmt_title(&self) -> Option<String> {
             self.recipe_tables.get(self.current_table_idx)
-                .map(|t| t.overview.name.clone())
+                .map(|t| format!("{} ({} pricing)", t.overview.name, t.overview.pricing))
         }
 
-        /// TODO: better way to do this...? Not very scalable
         fn fmt_header(&self) -> String {
-            format!(
-                "| {:<width0$} | {:>width1$} | {:>width2$} | {:>width3$} | {:>width4$} | {:>width5$} | {:>width6$} |",
-                DETAILED_ROW_HEADERS[0],
-                DETAILED_ROW_HEADERS[1],
-                DETAILED_ROW_HEADERS[2],
-                DETAILED_ROW_HEADERS[3],
-                DETAILED_ROW_HEADERS[4],
-                DETAILED_ROW_HEADERS[5],
-                DETAILED_ROW_HEADERS[6],
-                width0 = self.col_widths[0],
-                width1 = self.col_widths[1],
-                width2 = self.col_widths[2],
-                width3 = self.col_widths[3],
-                width4 = self.col_widths[4],
-                width5 = self.col_widths[5],
-                width6 = self.col_widths[6],
-            )
+            fmt_aligned_cells(&DETAILED_ROW_HEADERS, &self.col_widths, &Self::LEFT_ALIGNED)
         }
 
         // Formats the internal table containing all table body sections
         // TODO: Change API of program... currently ignores the row argument...
         fn fmt_item(&self, _: &Self::Row) -> String {
-            let current_internal_table = &self.current_table_rows;
-
-            let mut body: Vec<String> = Vec::with_capacity(3 * Self::NUM_SECTION_HEADERS);
-            for row in current_internal_table {
-                let string_row = format!(
-                    "| {:<width0$} | {:>width1$} | {:>width2$} | {:>width3$} | {:>width4$} | {:>width5$} | {:>width6$} |",
-                    row[0],
-                    row[1],
-                    row[2],
-                    row[3],
-                    row[4],
-                    row[5],
-                    row[6],
-                    width0 = self.col_widths[0],
-                    width1 = self.col_widths[1],
-                    width2 = self.col_widths[2],
-                    width3 = self.col_widths[3],
-                    width4 = self.col_widths[4],
-                    width5 = self.col_widths[5],
-                    width6 = self.col_widths[6],
-                );
-
-                body.push(string_row);
-            }
-
-            body.join("\n")
+            self.current_table_rows.iter()
+                .map(|row| fmt_aligned_cells(row, &self.col_widths, &Self::LEFT_ALIGNED))
+                .collect::<Vec<_>>()
+                .join("\n")
         }
 
         fn table_separator(&self) -> String {
@@ -210,10 +234,10 @@ pub mod markdown {
 
         // Combines normal and price margin sections into a singular table
         fn write_table(&mut self, f: &mut impl io::Write) -> io::Result<()> {
-            let current_internal_table = &self.recipe_tables[self.current_table_idx];
-
             // Print title
-            writeln!(f, "{}\n", current_internal_table.overview.name)?;
+            if let Some(title) = self.fmt_title() {
+                writeln!(f, "{title}\n")?;
+            }
 
             // Calculate cell padding
             // Creates the internal table body
@@ -222,8 +246,7 @@ pub mod markdown {
             // Print header
             writeln!(f, "{}", self.fmt_header())?;
             // and header separator
-            let separator_cells = self.col_widths.iter().map(|w| "-".repeat(*w.max(&3)));
-            writeln!(f, "| {} |", separator_cells.collect::<Vec<_>>().join(" | "))?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
 
             // TODO: Not working currently
             //  2025-10-28: Is this still the case?
@@ -231,6 +254,23 @@ pub mod markdown {
             let unused = &DetailedTable::default();
             writeln!(f, "{}", self.fmt_item(unused))?;
 
+            if let Some(sensitivity) = &self.recipe_tables[self.current_table_idx].sensitivity {
+                Self::write_sensitivity(f, sensitivity)?;
+            }
+
+            // Optional recent price history
+            if let Some(history) = &self.recipe_tables[self.current_table_idx].price_history {
+                Self::write_price_history(f, history)?;
+            }
+
+            let chart_links = &self.recipe_tables[self.current_table_idx].chart_links;
+            if !chart_links.is_empty() {
+                let links: Vec<String> = chart_links.iter()
+                    .map(|(label, link)| format!("[{label}]({link})"))
+                    .collect();
+                writeln!(f, "\nCharts: {}", links.join(" | "))?;
+            }
+
             // Print table separator
             writeln!(f, "\n{}\n", self.table_separator())?;
 
@@ -244,6 +284,8 @@ pub mod markdown {
         }
 
         fn write_all_tables(&mut self, writer: &mut impl io::Write) -> io::Result<()> {
+            write_prices_fetched_at(writer, self.prices_fetched_at)?;
+
             let mut num_iterations: usize = 0;
             let num_tables: usize = self.recipe_tables.len();
             while let Some(table) = self.recipe_tables.get(self.current_table_idx) {
@@ -263,11 +305,43 @@ pub mod markdown {
     }
 
     impl OptimalOverview {
+        /// Method, buy limit resets and limited by
+        const LEFT_ALIGNED: [usize; 3] = [0, 6, 7];
+
         pub fn new(overview_rows: Vec<OverviewRow>, col_widths: [usize; OVERVIEW_NUM_HEADERS]) -> Self {
             OptimalOverview {
                 overview_rows,
                 col_widths,
+                chart_links: HashMap::new(),
+                prices_fetched_at: None,
+                pricing: None,
+            }
+        }
+
+        pub fn set_prices_fetched_at(&mut self, prices_fetched_at: Option<i64>) {
+            self.prices_fetched_at = prices_fetched_at;
+        }
+
+        /// Default pricing strategy of the run, written above the table
+        pub fn set_pricing(&mut self, pricing: PricingStrategy) {
+            self.pricing = Some(pricing);
+        }
+
+        /// Recipes overriding the default pricing strategy, or priced from overridden prices, are marked
+        fn string_cells(&self, row: &OverviewRow) -> [String; OVERVIEW_NUM_HEADERS] {
+            let mut string_cells = row.to_string_cells();
+            if self.pricing.is_some_and(|pricing| pricing != row.pricing) {
+                string_cells[0] = format!("{} ({} pricing)", string_cells[0], row.pricing);
             }
+            if !row.overridden.is_empty() {
+                string_cells[0] = format!("{} (overridden prices)", string_cells[0]);
+            }
+            string_cells
+        }
+
+        /// Recipe name -> link to its profit chart
+        pub fn set_chart_links(&mut self, chart_links: HashMap<String, String>) {
+            self.chart_links = chart_links;
         }
 
         /// Update `col_widths` with maximum cell widths across all rows
@@ -281,7 +355,7 @@ pub mod markdown {
 
             // Check all data rows lengths
             for row in &self.overview_rows {
-                let string_cells = row.to_string_cells();
+                let string_cells = self.string_cells(row);
 
                 for (width, cell) in self.col_widths.iter_mut().zip(string_cells.iter()) {
                     *width = (*width).max(cell.len());
@@ -291,6 +365,8 @@ pub mod markdown {
     }
 
     impl DetailedRecipeLookup {
+        /// Item and limited by
+        const LEFT_ALIGNED: [usize; 2] = [0, 8];
         const NUM_SECTION_HEADERS: usize = 6;
         const BASE_HEADERS: [(&str, Option<&str>); Self::NUM_SECTION_HEADERS] = [
             ("Required Items", None),
@@ -301,17 +377,22 @@ pub mod markdown {
             ("Profit/Loss", Some("w/Tax")),
         ];
 
-        pub fn new(current_gp: i32, tables: Vec<DetailedTable>, widths: [usize;DETAILED_NUM_HEADERS]) -> Self {
+        pub fn new(current_gp: Gp, tables: Vec<DetailedTable>, widths: [usize;DETAILED_NUM_HEADERS]) -> Self {
             Self {
                 current_coins: current_gp,
                 recipe_tables: tables,
                 current_table_idx: 0,
                 col_widths: widths,
                 current_table_rows: Vec::new(),
+                prices_fetched_at: None,
             }
 
         }
 
+        pub fn set_prices_fetched_at(&mut self, prices_fetched_at: Option<i64>) {
+            self.prices_fetched_at = prices_fetched_at;
+        }
+
 
         // TODO: Convert to [String; Self::NUM_SECTION_HEADERS instead of Vec<String>
         fn generate_section_headers(percent_margin: f32) -> [String; Self::NUM_SECTION_HEADERS] {
@@ -352,7 +433,9 @@ pub mod markdown {
             }
         }
 
-        fn push_input_rows(res: &mut Vec<[String; DETAILED_NUM_HEADERS]>, inputs: &Vec<RecipeDetail>, number_recipes: i32) {
+        /// Items in `overridden` are marked, as their prices are not live
+        fn push_input_rows(res: &mut Vec<[String; DETAILED_NUM_HEADERS]>, inputs: &Vec<RecipeDetail>, number_recipes: i32,
+            overridden: &[String]) {
             for (name, price, quantity) in inputs {
                 let quantity: f64 = f64::from(quantity.to_owned());
                 let quantity_string = DetailedRecipeLookup::_format_quantity_string::<1>(
@@ -362,13 +445,20 @@ pub mod markdown {
                 let total_quantity = f64::from(number_recipes) * quantity;
                 let total_quantity_string = DetailedRecipeLookup::_format_quantity_string::<1>(total_quantity);
 
+                let name = if overridden.contains(name) {
+                    format!("{name} (overridden)")
+                } else {
+                    name.to_owned()
+                };
+
                 let row = [
-                    name.to_owned(),
+                    name,
                     quantity_string,
                     total_quantity_string,
                     price.to_comma_sep_string(),
-                    #[allow(clippy::cast_possible_truncation)]
-                    ((total_quantity * f64::from(*price)) as i32).to_comma_sep_string(),
+                    price.mul_f64(total_quantity, Rounding::Down).to_comma_sep_string(),
+                    String::new(),
+                    String::new(),
                     String::new(),
                     String::new(),
                 ];
@@ -388,7 +478,7 @@ pub mod markdown {
                 res.push(header);
 
                 // number_recipe = 1 since paying *once* for these items
-                Self::push_input_rows(res, pay_once, 1); 
+                Self::push_input_rows(res, pay_once, 1, &table.overview.overridden);
             }
 
 
@@ -400,17 +490,15 @@ pub mod markdown {
                 res.push(header);
 
                 // Check if inputs is empty
-                Self::push_input_rows(res, &table.inputs.inputs, number_recipe);
+                Self::push_input_rows(res, &table.inputs.inputs, number_recipe, &table.overview.overridden);
 
 
                 // Inputs Total
                 header = BLANK_LINE;
                 header[0].clone_from(&section_headers[2]);
 
-                #[allow(clippy::cast_possible_truncation)]
-                let single_input_price =  table.inputs.inputs.iter()
-                    .map(|(_,price,quantity)| (f64::from(*price) * f64::from(*quantity)) as i32).sum::<i32>();
-                header[4] = (single_input_price * number_recipe).to_comma_sep_string();
+                let single_input_price = DetailedTable::single_recipe_price(&table.inputs.inputs, Rounding::Up);
+                header[4] = single_input_price.times(i64::from(number_recipe)).to_comma_sep_string();
                 res.push(header);
             }
 
@@ -421,15 +509,13 @@ pub mod markdown {
             header[0].clone_from(&section_headers[3]);
             res.push(header);
 
-            Self::push_input_rows(res, &table.outputs, number_recipe);
+            Self::push_input_rows(res, &table.outputs, number_recipe, &table.overview.overridden);
 
             // Outputs Total (Taxed)
             header = BLANK_LINE;
             header[0].clone_from(&section_headers[4]);
-            #[allow(clippy::cast_possible_truncation)]
-            let single_output_price =  table.outputs.iter()
-                .map(|(_,price,quantity)| (f64::from(*price) * f64::from(*quantity)) as i32).sum::<i32>();
-            header[4] = (number_recipe * single_output_price).to_comma_sep_string();
+            let single_output_price = DetailedTable::single_recipe_price(&table.outputs, Rounding::Down);
+            header[4] = single_output_price.times(i64::from(number_recipe)).to_comma_sep_string();
             res.push(header);
 
             res.push(BLANK_LINE);
@@ -440,7 +526,9 @@ pub mod markdown {
             header[3] = table.overview.ideal_loss_gain().to_comma_sep_string();
             header[4] = table.overview.total_gp().to_comma_sep_string();
             header[5] = table.overview.format_time_string();
-            header[6] = table.overview.gph().to_comma_sep_string();
+            header[6] = table.overview.total_fill_time().map_or_else(String::new, |t| t.to_string());
+            header[7] = table.overview.gph().to_comma_sep_string();
+            header[8] = table.overview.limited_by.to_string();
             res.push(header);
 
         }
@@ -463,7 +551,10 @@ pub mod markdown {
                 .zip(pm)
                 .enumerate()
                 .map(|(c, (b, p))| {
-                    if (2 <= c) && !(b.is_empty() && p.is_empty()) {
+                    // Limited By is only repeated when the margin changes it
+                    if c == N - 1 && b == p {
+                        b
+                    } else if (2 <= c) && !(b.is_empty() && p.is_empty()) {
                         format!("{b} ({p})")
                     } else {
                         b
@@ -475,14 +566,14 @@ pub mod markdown {
         }
 
         fn adjust_prices(
-            items: &[(String, i32, f32)],
+            items: &[RecipeDetail],
             multiplier: f64,
-        ) -> Vec<(String, i32, f32)> {
+            rounding: Rounding,
+        ) -> Vec<RecipeDetail> {
             items.iter()
             .map(|(name, price, qty)| {
-                #[allow(clippy::cast_possible_truncation)]               
-                let adjusted = (f64::from(*price) * multiplier) as i32;
-                (name.clone(), adjusted.max(1), *qty)
+                let adjusted = price.mul_f64(multiplier, rounding);
+                (name.clone(), adjusted.max(Gp::new(1)), *qty)
             })
             .collect()
         }
@@ -503,8 +594,8 @@ pub mod markdown {
             // Update prices of inputs/outputs to reflect price margin
 
             // Increase buy prices and decrease sell prices
-            table.inputs.inputs = Self::adjust_prices(&table.inputs.inputs, (1.0 + percent_margin/100.0).into());
-            table.outputs = Self::adjust_prices(&table.outputs, (1.0 - percent_margin/100.0).into());
+            table.inputs.inputs = Self::adjust_prices(&table.inputs.inputs, (1.0 + percent_margin/100.0).into(), Rounding::Up);
+            table.outputs = Self::adjust_prices(&table.outputs, (1.0 - percent_margin/100.0).into(), Rounding::Down);
 
 
 
@@ -512,27 +603,29 @@ pub mod markdown {
             // Generate PM section
 
             // Decrease number of recipe
-            let input_cost_pm: i32 = DetailedTable::single_recipe_price(
-                &table.inputs.inputs
+            let input_cost_pm: Gp = DetailedTable::single_recipe_price(
+                &table.inputs.inputs, Rounding::Up
             );
 
-            // Current number is min(user_number_per_hour, effective_nph)
             // Since the cost will increase with a positive % margin
-            // Then effective_nph will decrease further
-            // --> output will be effective_nph
+            // the coins may now afford fewer than the current number.
+            // The current number over a single hour, so only coins can lower it
             let pm_number = crate::prices::prices::update_recipe_number(
                 Some(table.overview.number),
                 self.current_coins,
                 input_cost_pm,
-                table.overview.time_type
+                1.0
             );
             // Take minimum of the two numbers since
             // original table.overview.number is capped by the buy limit
-            table.overview.number = table.overview.number.min(pm_number);
+            if pm_number < table.overview.number {
+                table.overview.number = pm_number;
+                table.overview.limited_by = LimitingFactor::Coins;
+            }
 
             // Decrease profit of recipe
-            let output_cost_pm: i32 = DetailedTable::single_recipe_price(
-                &table.outputs
+            let output_cost_pm: Gp = DetailedTable::single_recipe_price(
+                &table.outputs, Rounding::Down
             );
             table.overview.profit = output_cost_pm - input_cost_pm;
 
@@ -550,6 +643,42 @@ pub mod markdown {
         }
 
 
+        /// Writes the recent price history as a separate table below the recipe
+        fn write_price_history(f: &mut impl io::Write, history: &PriceHistory) -> io::Result<()> {
+            let rows = history.to_string_cells();
+
+            let mut widths = HISTORY_ROW_HEADERS.map(str::len);
+            update_max_widths(&mut widths, &rows);
+
+            writeln!(f, "\nRecent Prices ({} timestep)\n", history.timestep)?;
+            writeln!(f, "{}", fmt_cells(&HISTORY_ROW_HEADERS, &widths))?;
+            writeln!(f, "{}", fmt_separator(&widths))?;
+
+            for row in &rows {
+                writeln!(f, "{}", fmt_cells(row, &widths))?;
+            }
+
+            Ok(())
+        }
+
+        /// Writes how far each price can move before the recipe breaks even, below the recipe
+        fn write_sensitivity(f: &mut impl io::Write, sensitivity: &PriceSensitivity) -> io::Result<()> {
+            let rows = sensitivity.to_string_cells();
+
+            let mut widths = SENSITIVITY_ROW_HEADERS.map(str::len);
+            update_max_widths(&mut widths, &rows);
+
+            writeln!(f, "\nPrice Sensitivity (inputs rising, outputs falling)\n")?;
+            writeln!(f, "{}", fmt_cells(&SENSITIVITY_ROW_HEADERS, &widths))?;
+            writeln!(f, "{}", fmt_separator(&widths))?;
+
+            for row in &rows {
+                writeln!(f, "{}", fmt_cells(row, &widths))?;
+            }
+
+            Ok(())
+        }
+
         fn _set_max_widths<I, T>(widths: &mut[usize; DETAILED_NUM_HEADERS],
             // new: [usize; DETAILED_NUM_HEADERS]) {
             new: I)
@@ -596,6 +725,524 @@ pub mod markdown {
         }
     }
 
+    /// Summary and per-snapshot tables of a backtest
+    pub struct BacktestOverview {
+        from: i64, // Unix Timestamp
+        to: i64,
+        series: Vec<BacktestSeries>,
+        col_widths: [usize; BACKTEST_NUM_HEADERS],
+    }
+
+    impl ResultsTable for BacktestOverview {
+        type Row = BacktestSeries;
+
+        fn fmt_title(&self) -> Option<String> {
+            Some(format!(
+                "Backtest from {} to {}",
+                format_timestamp(self.from),
+                format_timestamp(self.to),
+            ))
+        }
+
+        fn fmt_header(&self) -> String {
+            fmt_cells(&BACKTEST_ROW_HEADERS, &self.col_widths)
+        }
+
+        fn fmt_item(&self, row: &Self::Row) -> String {
+            fmt_cells(&Self::to_string_cells(row), &self.col_widths)
+        }
+
+        fn table_separator(&self) -> String {
+            "#".repeat(self.col_widths.iter().sum::<usize>() + 3 * BACKTEST_NUM_HEADERS + 1)
+        }
+
+        /// Writes the summary of every method
+        fn write_table(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.update_widths();
+
+            if let Some(title) = self.fmt_title() {
+                writeln!(f, "{title}\n")?;
+            }
+
+            writeln!(f, "{}", self.fmt_header())?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
+
+            for series in &self.series {
+                writeln!(f, "{}", self.fmt_item(series))?;
+            }
+
+            Ok(())
+        }
+
+        /// Writes the summary, then the values at each snapshot for every method
+        fn write_all_tables(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.write_table(f)?;
+
+            for series in &self.series {
+                writeln!(f, "\n{}\n", self.table_separator())?;
+                Self::write_series(f, series)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl BacktestOverview {
+        pub fn new(from: i64, to: i64, series: Vec<BacktestSeries>) -> Self {
+            Self {
+                from,
+                to,
+                series,
+                col_widths: [0; BACKTEST_NUM_HEADERS],
+            }
+        }
+
+        fn fmt_stats(stats: Option<SeriesStats>) -> [String; 3] {
+            #[allow(clippy::cast_possible_truncation)]
+            stats.map_or_else(
+                || [const { String::new() }; 3],
+                |s| [
+                    (s.mean.round() as i64).to_comma_sep_string(),
+                    (s.std_dev.round() as i64).to_comma_sep_string(),
+                    s.worst.to_comma_sep_string(),
+                ],
+            )
+        }
+
+        fn to_string_cells(series: &BacktestSeries) -> [String; BACKTEST_NUM_HEADERS] {
+            let [mean_profit, std_profit, worst_profit] = Self::fmt_stats(series.profit_stats());
+            let [mean_gph, std_gph, worst_gph] = Self::fmt_stats(series.gph_stats());
+
+            [
+                series.name.clone(),
+                series.points.len().to_comma_sep_string(),
+                mean_profit,
+                std_profit,
+                worst_profit,
+                mean_gph,
+                std_gph,
+                worst_gph,
+            ]
+        }
+
+        fn write_series(f: &mut impl io::Write, series: &BacktestSeries) -> io::Result<()> {
+            let rows: Vec<[String; SERIES_NUM_HEADERS]> = series.points.iter()
+                .map(|point| [
+                    format_timestamp(point.fetched_at),
+                    point.overview.total_gp().to_comma_sep_string(),
+                    point.overview.format_time_string(),
+                    point.overview.gph().to_comma_sep_string(),
+                ])
+                .collect();
+
+            let mut widths = SERIES_ROW_HEADERS.map(str::len);
+            update_max_widths(&mut widths, &rows);
+
+            writeln!(f, "{}\n", series.name)?;
+            writeln!(f, "{}", fmt_cells(&SERIES_ROW_HEADERS, &widths))?;
+            writeln!(f, "{}", fmt_separator(&widths))?;
+            for row in &rows {
+                writeln!(f, "{}", fmt_cells(row, &widths))?;
+            }
+
+            Ok(())
+        }
+
+        /// Update `col_widths` with maximum cell widths across all rows
+        pub fn update_widths(&mut self) {
+            self.col_widths = BACKTEST_ROW_HEADERS.map(str::len);
+
+            let rows: Vec<_> = self.series.iter().map(Self::to_string_cells).collect();
+            update_max_widths(&mut self.col_widths, &rows);
+        }
+    }
+
+    /// Recipes chosen to split the bankroll across, and their totals
+    pub struct AllocationOverview {
+        plan: AllocationPlan,
+        col_widths: [usize; ALLOCATION_NUM_HEADERS],
+    }
+
+    impl ResultsTable for AllocationOverview {
+        type Row = Allocation;
+
+        fn fmt_title(&self) -> Option<String> {
+            let limits = &self.plan.limits;
+            Some(format!(
+                "Allocation of {} GP over {} hours and {} GE slots",
+                limits.bankroll, limits.hours, limits.ge_slots,
+            ))
+        }
+
+        fn fmt_header(&self) -> String {
+            fmt_cells(&ALLOCATION_ROW_HEADERS, &self.col_widths)
+        }
+
+        fn fmt_item(&self, row: &Self::Row) -> String {
+            fmt_cells(&Self::to_string_cells(row), &self.col_widths)
+        }
+
+        fn table_separator(&self) -> String {
+            "#".repeat(self.col_widths.iter().sum::<usize>() + 3 * ALLOCATION_NUM_HEADERS + 1)
+        }
+
+        /// Writes every allocation, then the totals of the plan
+        fn write_table(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.update_widths();
+
+            if let Some(title) = self.fmt_title() {
+                writeln!(f, "{title}\n")?;
+            }
+
+            writeln!(f, "{}", self.fmt_header())?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
+
+            for allocation in &self.plan.allocations {
+                writeln!(f, "{}", self.fmt_item(allocation))?;
+            }
+            writeln!(f, "{}", fmt_cells(&self.total_cells(), &self.col_widths))?;
+
+            let unallocated = self.plan.limits.bankroll - self.plan.total_cost();
+            writeln!(f, "\nUsing {} of {} GE slots. {unallocated} GP unallocated.",
+                self.plan.ge_slots_used, self.plan.limits.ge_slots)?;
+
+            Ok(())
+        }
+
+        fn write_all_tables(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            // Only one table to write
+            self.write_table(f)
+        }
+    }
+
+    impl AllocationOverview {
+        pub fn new(plan: AllocationPlan) -> Self {
+            Self {
+                plan,
+                col_widths: [0; ALLOCATION_NUM_HEADERS],
+            }
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        fn format_hours(time_sec: f64) -> String {
+            f_round((time_sec / 3600.0) as f32, 2).to_string()
+        }
+
+        fn to_string_cells(allocation: &Allocation) -> [String; ALLOCATION_NUM_HEADERS] {
+            [
+                allocation.name.clone(),
+                allocation.number.to_comma_sep_string(),
+                allocation.cost.to_comma_sep_string(),
+                allocation.profit.to_comma_sep_string(),
+                Self::format_hours(allocation.time_sec),
+                allocation.gph().to_comma_sep_string(),
+            ]
+        }
+
+        fn total_cells(&self) -> [String; ALLOCATION_NUM_HEADERS] {
+            [
+                "Total".to_string(),
+                String::new(),
+                self.plan.total_cost().to_comma_sep_string(),
+                self.plan.total_profit().to_comma_sep_string(),
+                Self::format_hours(self.plan.total_time_sec()),
+                self.plan.gph().to_comma_sep_string(),
+            ]
+        }
+
+        /// Update `col_widths` with maximum cell widths across all rows
+        pub fn update_widths(&mut self) {
+            self.col_widths = ALLOCATION_ROW_HEADERS.map(str::len);
+
+            let mut rows: Vec<_> = self.plan.allocations.iter().map(Self::to_string_cells).collect();
+            rows.push(self.total_cells());
+            update_max_widths(&mut self.col_widths, &rows);
+        }
+    }
+
+    /// Items to flip, ranked by profit over their buy limit
+    pub struct FlipsOverview {
+        flips: Vec<Flip>,
+        prices_fetched_at: Option<i64>,
+        col_widths: [usize; FLIPS_NUM_HEADERS],
+    }
+
+    impl ResultsTable for FlipsOverview {
+        type Row = Flip;
+
+        fn fmt_title(&self) -> Option<String> {
+            Some("Flips (buy at low, sell at high)".to_string())
+        }
+
+        fn fmt_header(&self) -> String {
+            fmt_cells(&FLIPS_ROW_HEADERS, &self.col_widths)
+        }
+
+        fn fmt_item(&self, row: &Self::Row) -> String {
+            fmt_cells(&Self::to_string_cells(row), &self.col_widths)
+        }
+
+        fn table_separator(&self) -> String {
+            "#".repeat(self.col_widths.iter().sum::<usize>() + 3 * FLIPS_NUM_HEADERS + 1)
+        }
+
+        fn write_table(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.update_widths();
+
+            if let Some(title) = self.fmt_title() {
+                writeln!(f, "{title}\n")?;
+            }
+            write_prices_fetched_at(f, self.prices_fetched_at)?;
+
+            writeln!(f, "{}", self.fmt_header())?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
+
+            for flip in &self.flips {
+                writeln!(f, "{}", self.fmt_item(flip))?;
+            }
+
+            Ok(())
+        }
+
+        fn write_all_tables(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            // Only one table to write
+            self.write_table(f)
+        }
+    }
+
+    impl FlipsOverview {
+        pub fn new(flips: Vec<Flip>, prices_fetched_at: Option<i64>) -> Self {
+            Self {
+                flips,
+                prices_fetched_at,
+                col_widths: [0; FLIPS_NUM_HEADERS],
+            }
+        }
+
+        fn to_string_cells(flip: &Flip) -> [String; FLIPS_NUM_HEADERS] {
+            [
+                flip.name.clone(),
+                flip.buy.to_comma_sep_string(),
+                flip.sell.to_comma_sep_string(),
+                flip.margin.to_comma_sep_string(),
+                format!("{:.1}%", flip.roi()),
+                flip.limit.to_comma_sep_string(),
+                flip.capital().to_comma_sep_string(),
+                flip.profit().to_comma_sep_string(),
+                #[allow(clippy::cast_possible_truncation)]
+                flip.hourly_volume.map_or_else(String::new, |volume| (volume.round() as i64).to_comma_sep_string()),
+                flip.price_age.map_or_else(String::new, format_age),
+            ]
+        }
+
+        /// Update `col_widths` with maximum cell widths across all rows
+        pub fn update_widths(&mut self) {
+            self.col_widths = FLIPS_ROW_HEADERS.map(str::len);
+
+            let rows: Vec<_> = self.flips.iter().map(Self::to_string_cells).collect();
+            update_max_widths(&mut self.col_widths, &rows);
+        }
+    }
+
+    /// Recipes made along with their intermediate items, then what was made or bought for each
+    pub struct ChainOverview {
+        chains: Vec<RecipeChain>,
+        prices_fetched_at: Option<i64>,
+        col_widths: [usize; CHAINS_NUM_HEADERS],
+    }
+
+    impl ResultsTable for ChainOverview {
+        type Row = RecipeChain;
+
+        fn fmt_title(&self) -> Option<String> {
+            Some("Recipe chains (making intermediate items vs buying them)".to_string())
+        }
+
+        fn fmt_header(&self) -> String {
+            fmt_cells(&CHAINS_ROW_HEADERS, &self.col_widths)
+        }
+
+        fn fmt_item(&self, row: &Self::Row) -> String {
+            fmt_cells(&Self::to_string_cells(row), &self.col_widths)
+        }
+
+        fn table_separator(&self) -> String {
+            "#".repeat(self.col_widths.iter().sum::<usize>() + 3 * CHAINS_NUM_HEADERS + 1)
+        }
+
+        /// Writes the summary of every chain
+        fn write_table(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.update_widths();
+
+            if let Some(title) = self.fmt_title() {
+                writeln!(f, "{title}\n")?;
+            }
+            write_prices_fetched_at(f, self.prices_fetched_at)?;
+
+            writeln!(f, "{}", self.fmt_header())?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
+
+            for chain in &self.chains {
+                writeln!(f, "{}", self.fmt_item(chain))?;
+            }
+
+            Ok(())
+        }
+
+        fn write_all_tables(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.write_table(f)?;
+
+            for chain in &self.chains {
+                writeln!(f, "\n{}\n", self.table_separator())?;
+                Self::write_steps(f, chain)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl ChainOverview {
+        pub fn new(chains: Vec<RecipeChain>, prices_fetched_at: Option<i64>) -> Self {
+            Self {
+                chains,
+                prices_fetched_at,
+                col_widths: [0; CHAINS_NUM_HEADERS],
+            }
+        }
+
+        fn to_string_cells(chain: &RecipeChain) -> [String; CHAINS_NUM_HEADERS] {
+            let made: Vec<&str> = chain.made_items().map(String::as_str).collect();
+            [
+                chain.name.clone(),
+                chain.buying.map_or_else(|| "N/A".to_string(), |buying| buying.gp_per_hour().to_comma_sep_string()),
+                chain.chained.gp_per_hour().to_comma_sep_string(),
+                chain.chained.profit().to_comma_sep_string(),
+                chain.chained.cost.to_comma_sep_string(),
+                format!("{:.1}", chain.chained.time_sec),
+                made.join(", "),
+            ]
+        }
+
+        /// Each intermediate item, indented by how deep in the chain it is
+        fn step_cells(step: &ChainStep) -> [String; CHAIN_STEP_NUM_HEADERS] {
+            let decision = if step.made { "Make" } else { "Buy" };
+            [
+                format!("{}{}", "> ".repeat(step.depth), step.item),
+                format!("{:.2}", step.quantity),
+                step.buy_price.map_or_else(|| "N/A".to_string(), ToCommaString::to_comma_sep_string),
+                step.make.as_ref().map_or_else(String::new, |make| make.unit_cost.to_comma_sep_string()),
+                step.make.as_ref().map_or_else(String::new, |make| format!("{:.1}", make.unit_time_sec)),
+                step.make.as_ref().map_or_else(String::new, |make| make.recipe.clone()),
+                decision.to_string(),
+            ]
+        }
+
+        fn write_steps(f: &mut impl io::Write, chain: &RecipeChain) -> io::Result<()> {
+            let rows: Vec<_> = chain.steps.iter().map(Self::step_cells).collect();
+
+            let mut widths = CHAIN_STEP_ROW_HEADERS.map(str::len);
+            update_max_widths(&mut widths, &rows);
+
+            writeln!(f, "{} (per recipe)\n", chain.name)?;
+            writeln!(f, "{}", fmt_cells(&CHAIN_STEP_ROW_HEADERS, &widths))?;
+            writeln!(f, "{}", fmt_separator(&widths))?;
+
+            for row in &rows {
+                writeln!(f, "{}", fmt_cells(row, &widths))?;
+            }
+
+            Ok(())
+        }
+
+        /// Update `col_widths` with maximum cell widths across all rows
+        pub fn update_widths(&mut self) {
+            self.col_widths = CHAINS_ROW_HEADERS.map(str::len);
+
+            let rows: Vec<_> = self.chains.iter().map(Self::to_string_cells).collect();
+            update_max_widths(&mut self.col_widths, &rows);
+        }
+    }
+
+    /// Spread of each recipe's profit over simulated sessions
+    pub struct SimulationOverview {
+        simulation: Simulation,
+        col_widths: [usize; SIMULATION_NUM_HEADERS],
+    }
+
+    impl ResultsTable for SimulationOverview {
+        type Row = ProfitDistribution;
+
+        fn fmt_title(&self) -> Option<String> {
+            Some(format!(
+                "Profit over {} hour sessions, {} runs (seed {})",
+                self.simulation.session_hours, self.simulation.runs, self.simulation.seed,
+            ))
+        }
+
+        fn fmt_header(&self) -> String {
+            fmt_cells(&SIMULATION_ROW_HEADERS, &self.col_widths)
+        }
+
+        fn fmt_item(&self, row: &Self::Row) -> String {
+            fmt_cells(&Self::to_string_cells(row), &self.col_widths)
+        }
+
+        fn table_separator(&self) -> String {
+            "#".repeat(self.col_widths.iter().sum::<usize>() + 3 * SIMULATION_NUM_HEADERS + 1)
+        }
+
+        fn write_table(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            self.update_widths();
+
+            if let Some(title) = self.fmt_title() {
+                writeln!(f, "{title}\n")?;
+            }
+
+            writeln!(f, "{}", self.fmt_header())?;
+            writeln!(f, "{}", fmt_separator(&self.col_widths))?;
+
+            for distribution in &self.simulation.distributions {
+                writeln!(f, "{}", self.fmt_item(distribution))?;
+            }
+
+            Ok(())
+        }
+
+        fn write_all_tables(&mut self, f: &mut impl io::Write) -> io::Result<()> {
+            // Only one table to write
+            self.write_table(f)
+        }
+    }
+
+    impl SimulationOverview {
+        pub fn new(simulation: Simulation) -> Self {
+            Self {
+                simulation,
+                col_widths: [0; SIMULATION_NUM_HEADERS],
+            }
+        }
+
+        fn to_string_cells(distribution: &ProfitDistribution) -> [String; SIMULATION_NUM_HEADERS] {
+            [
+                distribution.name.clone(),
+                distribution.number.to_comma_sep_string(),
+                distribution.expected.to_comma_sep_string(),
+                distribution.p5.to_comma_sep_string(),
+                distribution.median.to_comma_sep_string(),
+                distribution.p95.to_comma_sep_string(),
+                format!("{:.1}%", distribution.loss_chance * 100.0),
+            ]
+        }
+
+        /// Update `col_widths` with maximum cell widths across all rows
+        pub fn update_widths(&mut self) {
+            self.col_widths = SIMULATION_ROW_HEADERS.map(str::len);
+
+            let rows: Vec<_> = self.simulation.distributions.iter().map(Self::to_string_cells).collect();
+            update_max_widths(&mut self.col_widths, &rows);
+        }
+    }
+
     #[cfg(test)]
     mod test {
         use super::*;
@@ -607,14 +1254,20 @@ pub mod markdown {
             let row = OverviewRow {
                 name: "Humidify Clay".to_string(),
                 pay_once_total: None,
-                profit: 375,
+                profit: Gp::new(375),
                 time_sec: Some(3.6),
                 number: 1_571,
-                time_type: crate::prices::prices::TimeType::SingleHour,
+                price_age: Some(2 * 60 * 60),
+                stale: true,
+                pricing: crate::config::PricingStrategy::Instant,
+                limit_reset: Some(("Clay".to_string(), 90 * 60)),
+                limited_by: LimitingFactor::BuyLimit("Clay".to_string()),
+                fill_sec: None,
+                overridden: Vec::new(),
             };
             let formatter = OptimalOverview::default();
 
-            let expected = "| Humidify Clay | 375 | 589,125 | 1.57 | 375,000 |";
+            let expected = "| Humidify Clay | 375 | 589,125 | 1.57 | 375,000 | 2h (stale) | Clay in 1h | Buy limit: Clay |";
             assert_eq!(
                 formatter.fmt_item(&row),
                 expected,
@@ -631,3 +1284,233 @@ pub mod markdown {
         // }
     }
 }
+
+/// Self-contained SVG line charts
+/// Rendered offline; no scripts or external resources are referenced
+pub mod svg {
+    use std::fmt::Write as _;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use crate::file_io::{FileIO, FileOptions};
+    use crate::helpers::{format_timestamp, ToCommaString};
+    use crate::item_search::data_types::latest::PriceDatum;
+    use crate::prices::backtest::BacktestSeries;
+
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 400.0;
+    // Space for axis labels, title and legend
+    const MARGIN_LEFT: f64 = 90.0;
+    const MARGIN_RIGHT: f64 = 20.0;
+    const MARGIN_TOP: f64 = 40.0;
+    const MARGIN_BOTTOM: f64 = 70.0;
+    const NUM_Y_TICKS: u32 = 5;
+    const NUM_X_TICKS: u32 = 4;
+
+    pub const COLOURS: [&str; 4] = ["#1f77b4", "#d62728", "#2ca02c", "#ff7f0e"];
+
+    #[derive(Debug, Clone, Default)]
+    pub struct LineSeries {
+        pub label: String,
+        // (Unix Timestamp, value)
+        pub points: Vec<(i64, f64)>,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct LineChart {
+        pub title: String,
+        pub y_label: String,
+        pub series: Vec<LineSeries>,
+    }
+
+    /// Escape text for use inside SVG elements
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    /// File name friendly version of `name`
+    pub fn slug(name: &str) -> String {
+        let mut slug = String::with_capacity(name.len());
+        for c in name.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.ends_with('_') {
+                slug.push('_');
+            }
+        }
+        slug.trim_matches('_').to_string()
+    }
+
+    /// Path of `target` for a markdown link inside `results_file`
+    /// Relative when `target` is under the same directory
+    pub fn link_path(results_file: &str, target: &Path) -> String {
+        let parent = Path::new(results_file).parent().unwrap_or(Path::new(""));
+        target.strip_prefix(parent)
+            .unwrap_or(target)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Total profit and GP/h of a recipe at each stored snapshot
+    pub fn recipe_profit_chart(series: &BacktestSeries) -> LineChart {
+        let mut chart = LineChart::new(format!("{} profit history", series.name), "GP".to_string());
+
+        let total: Vec<(i64, f64)> = series.points.iter()
+            .map(|p| (p.fetched_at, p.overview.total_gp().to_f64()))
+            .collect();
+        let gph: Vec<(i64, f64)> = series.points.iter()
+            .map(|p| (p.fetched_at, p.overview.gph().to_f64()))
+            .collect();
+
+        chart.add_series("(Total) Loss/Gain", total);
+        chart.add_series("GP/h", gph);
+        chart
+    }
+
+    /// High and low prices of an item at each stored snapshot
+    pub fn item_price_chart(item_name: &str, history: &[(i64, PriceDatum)]) -> LineChart {
+        let mut chart = LineChart::new(format!("{item_name} price history"), "GP".to_string());
+
+        let high: Vec<(i64, f64)> = history.iter()
+            .filter_map(|(t, d)| d.high.map(|p| (*t, f64::from(p))))
+            .collect();
+        let low: Vec<(i64, f64)> = history.iter()
+            .filter_map(|(t, d)| d.low.map(|p| (*t, f64::from(p))))
+            .collect();
+
+        chart.add_series("High", high);
+        chart.add_series("Low", low);
+        chart
+    }
+
+    impl LineChart {
+        pub fn new<S: Into<String>>(title: S, y_label: S) -> Self {
+            Self {
+                title: title.into(),
+                y_label: y_label.into(),
+                series: Vec::new(),
+            }
+        }
+
+        pub fn add_series<S: Into<String>>(&mut self, label: S, points: Vec<(i64, f64)>) {
+            self.series.push(LineSeries { label: label.into(), points });
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.series.iter().all(|s| s.points.is_empty())
+        }
+
+        /// (min, max) of x and y across all series, padded so neither range is empty
+        fn bounds(&self) -> ((i64, i64), (f64, f64)) {
+            let points = || self.series.iter().flat_map(|s| s.points.iter());
+
+            let x_min = points().map(|p| p.0).min().unwrap_or(0);
+            let x_max = points().map(|p| p.0).max().unwrap_or(0).max(x_min + 1);
+
+            let y_min = points().map(|p| p.1).fold(f64::INFINITY, f64::min);
+            let y_max = points().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+            let (y_min, y_max) = if y_min.is_finite() { (y_min, y_max) } else { (0.0, 0.0) };
+            let padding = ((y_max - y_min) * 0.05).max(1.0);
+
+            ((x_min, x_max), (y_min - padding, y_max + padding))
+        }
+
+        /// Render as a standalone SVG document
+        #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+        pub fn to_svg(&self) -> String {
+            let ((x_min, x_max), (y_min, y_max)) = self.bounds();
+            let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+            let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+
+            let x_pos = |x: i64| MARGIN_LEFT + (x - x_min) as f64 / (x_max - x_min) as f64 * plot_width;
+            let y_pos = |y: f64| MARGIN_TOP + (y_max - y) / (y_max - y_min) * plot_height;
+
+            // Writing to a String can't fail
+            let mut svg = String::new();
+            let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="11">"#);
+            let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+            let _ = writeln!(svg, r#"<text x="{}" y="22" text-anchor="middle" font-size="15">{}</text>"#, WIDTH / 2.0, escape(&self.title));
+
+            // Horizontal grid lines with y labels
+            for i in 0..=NUM_Y_TICKS {
+                let y = y_min + (y_max - y_min) * f64::from(i) / f64::from(NUM_Y_TICKS);
+                let py = y_pos(y);
+                let _ = writeln!(svg, r##"<line x1="{MARGIN_LEFT}" y1="{py:.1}" x2="{:.1}" y2="{py:.1}" stroke="#e0e0e0"/>"##, WIDTH - MARGIN_RIGHT);
+                let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#, MARGIN_LEFT - 6.0, py + 4.0, (y.round() as i64).to_comma_sep_string());
+            }
+
+            // Zero line when the values cross it, e.g. profit turning into a loss
+            if y_min < 0.0 && y_max > 0.0 {
+                let _ = writeln!(svg, r##"<line x1="{MARGIN_LEFT}" y1="{0:.1}" x2="{1:.1}" y2="{0:.1}" stroke="#808080" stroke-dasharray="4 3"/>"##, y_pos(0.0), WIDTH - MARGIN_RIGHT);
+            }
+
+            // x labels
+            for i in 0..=NUM_X_TICKS {
+                let x = x_min + ((x_max - x_min) as f64 * f64::from(i) / f64::from(NUM_X_TICKS)) as i64;
+                let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#, x_pos(x), HEIGHT - MARGIN_BOTTOM + 16.0, format_timestamp(x));
+            }
+
+            // Axes
+            let _ = writeln!(svg, r#"<polyline points="{MARGIN_LEFT},{MARGIN_TOP} {MARGIN_LEFT},{0} {1},{0}" fill="none" stroke="black"/>"#, HEIGHT - MARGIN_BOTTOM, WIDTH - MARGIN_RIGHT);
+            let _ = writeln!(svg, r#"<text transform="translate(16,{:.1}) rotate(-90)" text-anchor="middle">{}</text>"#, MARGIN_TOP + plot_height / 2.0, escape(&self.y_label));
+
+            // Lines and legend
+            for (i, series) in self.series.iter().enumerate() {
+                let colour = COLOURS[i % COLOURS.len()];
+                let points: Vec<String> = series.points.iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", x_pos(*x), y_pos(*y)))
+                    .collect();
+
+                if points.len() == 1 {
+                    let _ = writeln!(svg, r#"<circle cx="{}" cy="{}" r="3" fill="{colour}"/>"#, x_pos(series.points[0].0), y_pos(series.points[0].1));
+                } else {
+                    let _ = writeln!(svg, r#"<polyline points="{}" fill="none" stroke="{colour}" stroke-width="2"/>"#, points.join(" "));
+                }
+
+                let legend_x = MARGIN_LEFT + 160.0 * i as f64;
+                let legend_y = HEIGHT - 20.0;
+                let _ = writeln!(svg, r#"<rect x="{legend_x}" y="{}" width="12" height="12" fill="{colour}"/>"#, legend_y - 10.0);
+                let _ = writeln!(svg, r#"<text x="{}" y="{legend_y}">{}</text>"#, legend_x + 16.0, escape(&series.label));
+            }
+
+            svg.push_str("</svg>\n");
+            svg
+        }
+
+        /// Render and write to `directory/file_name`
+        /// Returns the path written to
+        /// # Errors
+        /// Errors if the directory can not be created or the file fails to be written
+        pub fn write_to(&self, directory: &str, file_name: &str) -> io::Result<PathBuf> {
+            std::fs::create_dir_all(directory)?;
+            let path = Path::new(directory).join(file_name);
+
+            let mut file = FileIO::new(path.to_string_lossy().into_owned(), FileOptions::new(true, true, true));
+            file.clear_contents()?;
+            io::Write::write_all(&mut file, self.to_svg().as_bytes())?;
+
+            Ok(path)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn chart_is_self_contained() {
+            let mut chart = LineChart::new("Tan <Leather> & co", "GP");
+            chart.add_series("Profit", vec![(0, -10.0), (3600, 25.0), (7200, 5.0)]);
+            let svg = chart.to_svg();
+
+            assert!(svg.starts_with("<svg"));
+            assert!(svg.contains("Tan &lt;Leather&gt; &amp; co"));
+            assert!(!svg.contains("<script"));
+            assert!(!svg.contains("href"));
+            assert_eq!(slug("Casting Tan Leather (Black d'hide)"), "casting_tan_leather_black_d_hide");
+        }
+    }
+}
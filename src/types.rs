@@ -1,21 +1,29 @@
-use std::io;
+use std::{fmt, io};
 
-use crate::{helpers::{f_round, ToCommaString}, prices::prices::TimeType};
+use crate::{
+    config::{PricingStrategy, TimeStep},
+    gp::{Gp, Rounding},
+    helpers::{f_round, format_age, ToCommaString},
+    item_search::data_types::timeseries::PriceSummary,
+};
 
 pub const SECOND_PER_TICK: f32 = 0.6;
 pub const SEC_IN_HOUR: u16 = 60 * 60;
 
-pub const OVERVIEW_NUM_HEADERS: usize = 5;
+pub const OVERVIEW_NUM_HEADERS: usize = 8;
 pub const OVERVIEW_ROW_HEADERS: [&str; OVERVIEW_NUM_HEADERS] = [
     "Method",
     "Loss/Gain",
     "(Total) Loss/Gain",
     "Time (Hours)",
     "GP/h",
+    "Price Age",
+    "Buy Limit Resets",
+    "Limited By",
 ];
 
 // TODO: Add an extra col at the start for profit/loss to be separated?
-pub const DETAILED_NUM_HEADERS: usize = 7;
+pub const DETAILED_NUM_HEADERS: usize = 9;
 pub const DETAILED_ROW_HEADERS: [&str; DETAILED_NUM_HEADERS] = [
     "Item",
     // TODO: Name
@@ -24,7 +32,108 @@ pub const DETAILED_ROW_HEADERS: [&str; DETAILED_NUM_HEADERS] = [
     "At Offer Price (GP)", // Individual recipe
     "Total Price (GP)",
     "Total Time (h)",
-    "Profit/Recipe Time (GP/h)"
+    "Est. Fill Time (h)",
+    "Profit/Recipe Time (GP/h)",
+    "Limited By",
+];
+
+pub const HISTORY_NUM_HEADERS: usize = 6;
+pub const HISTORY_ROW_HEADERS: [&str; HISTORY_NUM_HEADERS] = [
+    "Item",
+    "At Offer Price (GP)",
+    "Min (GP)",
+    "Max (GP)",
+    "Mean (GP)",
+    "Offer vs Mean",
+];
+
+pub const SENSITIVITY_NUM_HEADERS: usize = 8;
+pub const SENSITIVITY_ROW_HEADERS: [&str; SENSITIVITY_NUM_HEADERS] = [
+    "Item",
+    "Side",
+    "(Single Recipe Quantity)",
+    "At Offer Price (GP)",
+    "Break-even Price (GP)",
+    "Headroom",
+    "Profit per 1% Move (GP)",
+    "Total per 1% Move (GP)",
+];
+
+pub const BACKTEST_NUM_HEADERS: usize = 8;
+pub const BACKTEST_ROW_HEADERS: [&str; BACKTEST_NUM_HEADERS] = [
+    "Method",
+    "Snapshots",
+    "Mean (Total) Loss/Gain",
+    "Std Dev (Total) Loss/Gain",
+    "Worst (Total) Loss/Gain",
+    "Mean GP/h",
+    "Std Dev GP/h",
+    "Worst GP/h",
+];
+
+pub const ALLOCATION_NUM_HEADERS: usize = 6;
+pub const ALLOCATION_ROW_HEADERS: [&str; ALLOCATION_NUM_HEADERS] = [
+    "Method",
+    "Number",
+    "Capital (GP)",
+    "(Total) Loss/Gain",
+    "Time (Hours)",
+    "GP/h",
+];
+
+pub const FLIPS_NUM_HEADERS: usize = 10;
+pub const FLIPS_ROW_HEADERS: [&str; FLIPS_NUM_HEADERS] = [
+    "Item",
+    "Buy (Low)",
+    "Sell (High)",
+    "Margin w/Tax",
+    "ROI",
+    "Buy Limit",
+    "Capital (GP)",
+    "Profit per Limit (GP)",
+    "Volume/h",
+    "Price Age",
+];
+
+pub const SIMULATION_NUM_HEADERS: usize = 7;
+pub const SIMULATION_ROW_HEADERS: [&str; SIMULATION_NUM_HEADERS] = [
+    "Method",
+    "Number",
+    "Expected (GP)",
+    "5th Percentile (GP)",
+    "Median (GP)",
+    "95th Percentile (GP)",
+    "Chance of Loss",
+];
+
+pub const CHAINS_NUM_HEADERS: usize = 7;
+pub const CHAINS_ROW_HEADERS: [&str; CHAINS_NUM_HEADERS] = [
+    "Method",
+    "Buying All (GP/h)",
+    "Chained (GP/h)",
+    "Chained Loss/Gain",
+    "Chained Cost",
+    "Chained Time (s)",
+    "Made",
+];
+
+pub const CHAIN_STEP_NUM_HEADERS: usize = 7;
+pub const CHAIN_STEP_ROW_HEADERS: [&str; CHAIN_STEP_NUM_HEADERS] = [
+    "Item",
+    "Quantity",
+    "Buy Price",
+    "Make Cost",
+    "Make Time (s)",
+    "Made With",
+    "Decision",
+];
+
+pub const SERIES_NUM_HEADERS: usize = 4;
+pub const SERIES_ROW_HEADERS: [&str; SERIES_NUM_HEADERS] = [
+    "Fetched At",
+    "(Total) Loss/Gain",
+    "Time (Hours)",
+    "GP/h",
 ];
 
 pub trait ResultsTable {
@@ -60,15 +169,54 @@ pub trait ResultsTable {
 pub struct OverviewRow {
     // &str?
     pub name: String,
-    // Actually all integers* but i32 can be accurately represented by f64
-    // *apart from time which is in decimal hours (Lower abs value)
     // loss/gain, (total)loss/gain, time (hours), gph
     // Repeated values can be calculated by multiplying number of recipes made
-    pub pay_once_total: Option<i32>,
-    pub profit: i32, // Can be negative
+    pub pay_once_total: Option<Gp>,
+    pub profit: Gp, // Can be negative
     pub time_sec: Option<f32>,
     pub number: i32, // TODO: Cap at i32 limit if using u32
-    pub time_type: TimeType,
+    // Seconds since the oldest trade of any input or output price
+    pub price_age: Option<i64>,
+    pub stale: bool,
+    pub pricing: PricingStrategy,
+    // Limiting input item, and seconds until its buy limit resets. See `PurchaseLedger`
+    pub limit_reset: Option<(String, i64)>,
+    pub limited_by: LimitingFactor,
+    // Seconds for the offers of a single recipe to fill. See `config::Liquidity`
+    pub fill_sec: Option<f32>,
+    // Items priced from `profit.price_overrides` instead of loaded prices
+    pub overridden: Vec<String>,
+}
+
+/// What caps the number of recipes made
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum LimitingFactor {
+    /// Coins available to buy inputs
+    #[default]
+    Coins,
+    /// Buy limit of an input item
+    BuyLimit(String),
+    /// Traded volume of an item, over the session
+    Liquidity(String),
+    /// Total time is capped by the session length. See `profit.session_hours`
+    SessionLength,
+    /// `number_per_hour` of the recipe over the session
+    NumberPerHour,
+    /// Neither `number_per_hour` nor a cost bounds the number, so a single recipe is made
+    Default,
+}
+
+impl fmt::Display for LimitingFactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Coins => f.pad("Coins"),
+            Self::BuyLimit(item) => f.pad(&format!("Buy limit: {item}")),
+            Self::Liquidity(item) => f.pad(&format!("Volume: {item}")),
+            Self::SessionLength => f.pad("Session length"),
+            Self::NumberPerHour => f.pad("Number per hour"),
+            Self::Default => f.pad("Default"),
+        }
+    }
 }
 
 
@@ -83,25 +231,35 @@ pub struct DetailedTable {
     pub overview: OverviewRow,
     // Store item name, price, quantity FOR A SINGLE RECIPE
     pub inputs: TableInputs,
-    pub outputs: Vec<(String, i32, f32)>, // Ditto
+    pub outputs: Vec<RecipeDetail>, // Ditto
     pub percent_margin: f32, // 2.5% == 2.5
+    pub price_history: Option<PriceHistory>,
+    pub sensitivity: Option<PriceSensitivity>,
+    // (Label, link) to charts of the recipe and its items
+    pub chart_links: Vec<(String, String)>,
 }
 #[derive(Debug, Default, Clone)]
 pub struct TableInputs {
-    pub pay_once: Option<Vec<(String, i32, f32)>>,
-    pub inputs: Vec<(String, i32, f32)>,
+    pub pay_once: Option<Vec<RecipeDetail>>,
+    pub inputs: Vec<RecipeDetail>,
 }
 
 impl OverviewRow {
     /// Construct a new row 
-    pub fn new(name: String, pay_once_total: Option<i32>, profit: i32, time_sec: Option<f32>, number: i32, time_type: TimeType) -> Self {
+    pub fn new(name: String, pay_once_total: Option<Gp>, profit: Gp, time_sec: Option<f32>, number: i32) -> Self {
         OverviewRow {
             name,
             pay_once_total,
             profit,
             time_sec,
             number,
-            time_type,
+            price_age: None,
+            stale: false,
+            pricing: PricingStrategy::default(),
+            limit_reset: None,
+            limited_by: LimitingFactor::default(),
+            fill_sec: None,
+            overridden: Vec::new(),
         }
     }
 
@@ -115,37 +273,37 @@ impl OverviewRow {
         Some(f_round(unrounded, 2))
     }
 
-    pub fn ideal_loss_gain(&self) -> i32 {
+    /// Estimated hours for all offers to fill
+    pub fn total_fill_time(&self) -> Option<f32> {
+        #[allow(clippy::cast_precision_loss)]
+        let unrounded: f32 = self.fill_sec? * (self.number as f32) / f32::from(SEC_IN_HOUR);
+
+        Some(f_round(unrounded, 2))
+    }
+
+    pub fn ideal_loss_gain(&self) -> Gp {
         self.profit
     }
 
-    pub fn loss_gain(&self) -> i32 {
-        self.ideal_loss_gain().saturating_sub(
-            self.pay_once_total.unwrap_or(0)
-        )
+    pub fn loss_gain(&self) -> Gp {
+        self.ideal_loss_gain() - self.pay_once_total.unwrap_or(Gp::ZERO)
     }
 
-    pub fn ideal_total_gp(&self) -> i32 {
-        self.ideal_loss_gain().saturating_mul(self.number)
+    pub fn ideal_total_gp(&self) -> Gp {
+        self.ideal_loss_gain().times(i64::from(self.number))
     }
 
-    pub fn total_gp(&self) -> i32 {
+    pub fn total_gp(&self) -> Gp {
         // Compensate for removing once
-        self.ideal_total_gp().saturating_sub(
-            self.pay_once_total.unwrap_or(0)
-        )
+        self.ideal_total_gp() - self.pay_once_total.unwrap_or(Gp::ZERO)
     }
-    pub fn gph(&self) -> i32 {
-        #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
-        if self.time_sec.is_none() {
+
+    pub fn gph(&self) -> Gp {
+        match self.time_sec {
             // Use number_per_hour
             // self.number * self.total_gp()
-            self.total_gp() // Per hour already
-        } else {
-            (
-                f64::from(SEC_IN_HOUR) * f64::from(self.profit)
-                / f64::from(unsafe { self.time_sec.unwrap_unchecked() })
-            ).floor() as i32
+            None => self.total_gp(), // Per hour already
+            Some(time_sec) => self.profit.per_hour(f64::from(time_sec)),
         }
     }
 
@@ -155,6 +313,20 @@ impl OverviewRow {
         self.total_time()
             .map_or("1.0".to_string(), |t| t.to_string()) // Since number_per_hour
     }
+    pub fn format_price_age(&self) -> String {
+        let age = self.price_age.map_or_else(String::new, format_age);
+        if self.stale {
+            age + " (stale)"
+        } else {
+            age
+        }
+    }
+
+    pub fn format_limit_reset(&self) -> String {
+        self.limit_reset.as_ref()
+            .map_or_else(String::new, |(item, secs)| format!("{item} in {}", format_age(*secs)))
+    }
+
     pub fn to_string_cells(&self) -> [String; OVERVIEW_NUM_HEADERS] {
         [
             self.name.clone(),
@@ -162,12 +334,95 @@ impl OverviewRow {
             self.total_gp().to_comma_sep_string(),
             self.format_time_string(),
             self.gph().to_comma_sep_string(),
+            self.format_price_age(),
+            self.format_limit_reset(),
+            self.limited_by.to_string(),
         ]
     }
 }
 
 
-pub type RecipeDetail = (String, i32, f32); // Item name, price, quantity
+/// Recent prices of every item in a recipe
+#[derive(Debug, Default, Clone)]
+pub struct PriceHistory {
+    pub timestep: TimeStep,
+    // Item name, offer price, summary of the timeseries on the offer side
+    pub items: Vec<(String, Gp, PriceSummary)>,
+}
+
+impl PriceHistory {
+    /// Percentage difference of the offer price from the mean price
+    pub fn offer_vs_mean(price: Gp, summary: &PriceSummary) -> Option<f64> {
+        (summary.mean != 0).then(||
+            100.0 * (price.to_f64() - f64::from(summary.mean)) / f64::from(summary.mean)
+        )
+    }
+
+    pub fn to_string_cells(&self) -> Vec<[String; HISTORY_NUM_HEADERS]> {
+        self.items.iter()
+            .map(|(name, price, summary)| [
+                name.clone(),
+                price.to_comma_sep_string(),
+                summary.min.to_comma_sep_string(),
+                summary.max.to_comma_sep_string(),
+                summary.mean.to_comma_sep_string(),
+                Self::offer_vs_mean(*price, summary)
+                    .map_or_else(String::new, |p| format!("{p:+.1}%")),
+            ])
+            .collect()
+    }
+}
+
+/// How far each item's price can move against a recipe before it loses money.
+/// Inputs move up, outputs move down
+#[derive(Debug, Default, Clone)]
+pub struct PriceSensitivity {
+    pub number: i32,
+    pub items: Vec<ItemSensitivity>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemSensitivity {
+    pub name: String,
+    pub input: bool,
+    pub price: Gp,
+    pub quantity: f32,
+    /// Unit price where a single recipe makes no profit.
+    /// `None` when no price on this side does, e.g. an output that could be given away
+    pub break_even: Option<Gp>,
+    /// Change in the profit of a single recipe when the price moves 1% against it
+    pub per_percent: Gp,
+}
+
+impl ItemSensitivity {
+    /// Percentage the price can move against the recipe before it breaks even
+    pub fn headroom(&self) -> Option<f64> {
+        let break_even = self.break_even?;
+        (self.price != Gp::ZERO).then(|| {
+            let change = 100.0 * (break_even.to_f64() - self.price.to_f64()) / self.price.to_f64();
+            if self.input { change } else { -change }
+        })
+    }
+}
+
+impl PriceSensitivity {
+    pub fn to_string_cells(&self) -> Vec<[String; SENSITIVITY_NUM_HEADERS]> {
+        self.items.iter()
+            .map(|item| [
+                item.name.clone(),
+                (if item.input { "Input" } else { "Output" }).to_string(),
+                item.quantity.to_string(),
+                item.price.to_comma_sep_string(),
+                item.break_even.map_or_else(|| "Never".to_string(), ToCommaString::to_comma_sep_string),
+                item.headroom().map_or_else(String::new, |p| format!("{p:+.1}%")),
+                item.per_percent.to_comma_sep_string(),
+                item.per_percent.times(i64::from(self.number)).to_comma_sep_string(),
+            ])
+            .collect()
+    }
+}
+
+pub type RecipeDetail = (String, Gp, f32); // Item name, price, quantity
 impl DetailedTable {
     pub fn new(overview: OverviewRow, inputs: TableInputs, outputs: Vec<RecipeDetail>, percent_margin: f32) -> Self {
        Self {
@@ -175,6 +430,9 @@ impl DetailedTable {
            inputs,
            outputs,
            percent_margin,
+           price_history: None,
+           sensitivity: None,
+           chart_links: Vec::new(),
        } 
     }
 
@@ -186,11 +444,11 @@ impl DetailedTable {
     }
 
     // TODO: Ditto naming
-    pub fn single_recipe_price(inputs: &[RecipeDetail]) -> i32 {
-        #[allow(clippy::cast_possible_truncation)]
+    /// See [`crate::gp`] for which `rounding` to use
+    pub fn single_recipe_price(inputs: &[RecipeDetail], rounding: Rounding) -> Gp {
         inputs.iter()
             // Price * Quantity
-            .map(|(_, p, q)| (f64::from(*p) * f64::from(*q)) as i32)
+            .map(|(_, p, q)| p.mul_f64(f64::from(*q), rounding))
             .sum()
     }
 
@@ -201,10 +459,10 @@ impl DetailedTable {
         self.overview.number
     }
 
-    pub fn total_price(&self, inputs: &[RecipeDetail]) -> i32 {
-        let single_price: i32 = Self::single_recipe_price(inputs);
+    pub fn total_price(&self, inputs: &[RecipeDetail], rounding: Rounding) -> Gp {
+        let single_price: Gp = Self::single_recipe_price(inputs, rounding);
 
-        self.overview.number * single_price
+        single_price.times(i64::from(self.overview.number))
     }
 
     pub fn total_time(&self) -> Option<f32> {
@@ -0,0 +1,489 @@
+//! Offline runs of the whole pipeline, from a price source through
+//! `PriceHandle` to the markdown writers, against the checked-in fixtures.
+use std::{collections::HashMap, path::PathBuf};
+
+use osrs_gph::{
+    config::{self, FilePaths, FlipOptions, LookupDataPaths, OverviewFilter, OverviewSortBy, PriceOverride, PricingStrategy, StaleAction, StalePrices, TimeSpan, TimeStep},
+    gp::Gp,
+    item_search::{
+        data_types::latest::{PriceDataType, PriceDatum},
+        history::PriceHistory,
+        item_search::ItemSearch,
+        price_source::{FixtureSource, InMemorySource, PriceSource},
+        recipes::{DropQuantity, Recipe, RecipeBook, RecipeDrop, RecipeTime},
+    },
+    prices::{
+        allocation::{self, AllocationLimits},
+        backtest::backtest,
+        buy_limits::{Purchase, PurchaseLedger},
+        chains::evaluate_chains,
+        flips,
+        pareto_sort::custom_types::compute_weights,
+        prices::PriceHandle,
+        simulation::simulate,
+        tax::TaxRules,
+    },
+    results_writer::markdown::{DetailedRecipeLookup, OptimalOverview},
+    types::{LimitingFactor, ResultsTable, DETAILED_NUM_HEADERS, OVERVIEW_NUM_HEADERS},
+};
+
+const COINS: Gp = Gp::new(1_000_000);
+
+fn fixture_path(file: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(file)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn fixture_filepaths() -> FilePaths {
+    FilePaths {
+        lookup_data: LookupDataPaths {
+            id_to_name: fixture_path("id_to_name.yaml"),
+            name_to_id: fixture_path("name_to_id.yaml"),
+            api_mapping: fixture_path("mapping.yaml"),
+            recipes: fixture_path("recipes.yaml"),
+            // The real rules
+            ge_tax: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("lookup_data/ge_tax.yaml")
+                .to_string_lossy()
+                .into_owned(),
+        },
+        ..FilePaths::default()
+    }
+}
+
+fn price_handle(price_source: Box<dyn PriceSource>, timeseries_source: Box<dyn PriceSource>) -> PriceHandle {
+    let filepaths = fixture_filepaths();
+
+    let mut item_search = ItemSearch::new(HashMap::new(), filepaths.clone(), config::Api::default());
+    item_search.set_price_source(price_source);
+    item_search.set_timeseries_source(timeseries_source);
+
+    let item_prices = item_search.get_item_prices();
+    item_search.update_item_prices(item_prices);
+    item_search.update_item_extra_info(filepaths.lookup_data.api_mapping.clone()).unwrap();
+
+    let mut recipe_list = RecipeBook::new(HashMap::new());
+    recipe_list.load_default_recipes(filepaths.lookup_data.recipes);
+
+    let tax = TaxRules::load(filepaths.lookup_data.ge_tax);
+    PriceHandle::new(item_search, recipe_list, COINS, 1.5, StalePrices::default(), tax, PricingStrategy::default())
+}
+
+fn overview_markdown(price_handle: &PriceHandle) -> (Vec<String>, String) {
+    let mut display = config::Display::default();
+    display.filters[OverviewFilter::Reverse] = false;
+    let weights = compute_weights(COINS, &config::Weights::default());
+    let overview = price_handle.all_recipe_overview(&OverviewSortBy::GPH, &weights, &display);
+
+    let names = overview.iter().map(|row| row.name.clone()).collect();
+
+    let mut buffer = Vec::new();
+    let mut writer = OptimalOverview::new(overview, [0; OVERVIEW_NUM_HEADERS]);
+    writer.write_table(&mut buffer).unwrap();
+
+    (names, String::from_utf8(buffer).unwrap())
+}
+
+#[test]
+fn fixture_prices_to_markdown() {
+    let directory = fixture_path("prices");
+    let price_handle = price_handle(
+        Box::new(FixtureSource::new(&directory)),
+        Box::new(FixtureSource::new(&directory)),
+    );
+
+    // Sapphire rings lose money
+    let (names, markdown) = overview_markdown(&price_handle);
+    assert_eq!(names, vec!["Ruby Rings".to_string(), "Ruby Rings then HA".to_string()]);
+    // (1,300 - 2% tax) - (160 + 950)
+    assert!(markdown.contains("| Ruby Rings         |       164 |"), "{markdown}");
+    // Coins are never taxed: 1,215 - (160 + 950 + 100)
+    assert!(markdown.contains("| Ruby Rings then HA |         5 |"), "{markdown}");
+
+    let recipe = price_handle.recipe_list.get_recipe(&"Ruby Rings".to_string()).unwrap().clone();
+    let mut tables = vec![price_handle.recipe_lookup_from_recipe(&recipe).unwrap()];
+    price_handle.add_price_history(&mut tables, TimeStep::OneHour);
+
+    let history = tables[0].price_history.as_ref().unwrap();
+    let (_, offer, summary) = history.items.iter().find(|(name, ..)| name == "Ruby").unwrap();
+    assert_eq!((*offer, summary.min, summary.max, summary.mean), (Gp::new(950), 900, 990, 950));
+
+    // Profit of 164 a ring
+    let sensitivity = tables[0].sensitivity.as_ref().unwrap();
+    let line = |name: &str| sensitivity.items.iter().find(|item| item.name == name).unwrap();
+    assert_eq!((line("Ruby").break_even, line("Ruby").per_percent), (Some(Gp::new(1_114)), Gp::new(-10)));
+    // 1,132 - 22 tax = 1,274 - 164
+    assert_eq!((line("Ruby ring").break_even, line("Ruby ring").per_percent), (Some(Gp::new(1_132)), Gp::new(-12)));
+
+    let mut buffer = Vec::new();
+    let mut writer = DetailedRecipeLookup::new(COINS, tables, [0; DETAILED_NUM_HEADERS]);
+    writer.write_all_tables(&mut buffer).unwrap();
+    let markdown = String::from_utf8(buffer).unwrap();
+
+    assert!(markdown.starts_with("Ruby Rings"), "{markdown}");
+    assert!(markdown.contains("Ruby ring"));
+    assert!(markdown.contains("Price Sensitivity"), "{markdown}");
+}
+
+fn cheap_sapphire_prices() -> PriceDataType {
+    let datum = |high, low| PriceDatum {
+        high: Some(high),
+        high_time: Some(1_776_380_000),
+        low: Some(low),
+        low_time: Some(1_776_380_000),
+        ..Default::default()
+    };
+
+    let mut prices = PriceDataType::default();
+    for (id, high, low) in [
+        ("2357", 160, 150),   // Gold bar
+        ("1603", 950, 900),   // Ruby
+        ("1641", 1350, 1300), // Ruby ring
+        ("561", 100, 95),     // Nature rune
+        ("1607", 100, 90),    // Sapphire, now cheap
+        ("1637", 1000, 980),  // Sapphire ring
+    ] {
+        prices.add_datum(id.to_string(), datum(high, low));
+    }
+    prices
+}
+
+#[test]
+fn in_memory_prices_change_overview() {
+    let price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+
+    let (names, markdown) = overview_markdown(&price_handle);
+    // Highest GP/h first
+    assert_eq!(names, vec!["Sapphire Rings".to_string(), "Ruby Rings".to_string(), "Ruby Rings then HA".to_string()]);
+    // (980 - 2% tax) - (160 + 100)
+    assert!(markdown.contains("| Sapphire Rings     |       701 |"), "{markdown}");
+}
+
+#[test]
+fn price_overrides_are_marked() {
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+
+    let overrides = HashMap::from([("Sapphire ring".to_string(), PriceOverride { high: 500, low: 500 })]);
+    price_handle.all_items.set_price_overrides(overrides);
+    let mut item_prices = price_handle.all_items.get_item_prices();
+    price_handle.all_items.override_prices(&mut item_prices);
+    price_handle.all_items.update_item_prices(item_prices);
+
+    let (_, markdown) = overview_markdown(&price_handle);
+    // (500 - 2% tax) - (160 + 100)
+    assert!(markdown.contains("| Sapphire Rings (overridden prices) |       230 |"), "{markdown}");
+    assert!(markdown.contains("| Ruby Rings                         |"), "{markdown}");
+    assert!(markdown.contains("Overridden: Sapphire ring"), "{markdown}");
+}
+
+#[test]
+fn patient_pricing_changes_profit() {
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+    price_handle.pricing = PricingStrategy::Patient;
+
+    let (_, markdown) = overview_markdown(&price_handle);
+    // Buy at low, sell at high: (1,000 - 2% tax) - (150 + 90)
+    assert!(markdown.contains("| Sapphire Rings     |       740 |"), "{markdown}");
+
+    // Overridden recipes keep their own strategy
+    price_handle.recipe_list.recipes.get_mut("Sapphire Rings").unwrap().pricing = Some(PricingStrategy::Midpoint);
+    let (_, markdown) = overview_markdown(&price_handle);
+    // (990 - 2% tax) - (155 + 95)
+    assert!(markdown.contains("| Sapphire Rings     |       721 |"), "{markdown}");
+}
+
+#[test]
+fn stale_pay_once_item_excludes_recipe() {
+    let mut prices = cheap_sapphire_prices();
+    prices.add_datum("1603".to_string(), PriceDatum { // Ruby, last traded a day earlier
+        high: Some(950),
+        high_time: Some(1_776_380_000 - 24 * 60 * 60),
+        low: Some(900),
+        low_time: Some(1_776_380_000 - 24 * 60 * 60),
+        ..Default::default()
+    });
+    let mut price_handle = price_handle(Box::new(InMemorySource::new(prices)), Box::new(InMemorySource::default()));
+    price_handle.stale_prices = StalePrices { max_high_age_mins: Some(60), max_low_age_mins: Some(60), action: StaleAction::Exclude };
+
+    let recipe = price_handle.recipe_list.recipes.get_mut("Sapphire Rings").unwrap();
+    recipe.inputs.pay_once = Some(HashMap::from([("Ruby".to_string(), 1.0)]));
+    let recipe = recipe.clone();
+
+    assert!(price_handle.recipe_price_overview_from_recipe(&recipe).is_none());
+    assert!(price_handle.recipe_lookup_from_recipe(&recipe).is_none());
+
+    let limits = AllocationLimits { bankroll: COINS, hours: 1.0, ge_slots: 8 };
+    let plan = allocation::plan(&price_handle, limits, &config::Membership::default());
+    assert!(plan.allocations.iter().all(|allocation| allocation.name != "Sapphire Rings"));
+}
+
+#[test]
+fn buy_ledger_reduces_limit() {
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+    let mut ledger = PurchaseLedger::default();
+    ledger.record(Purchase { item: "Gold bar".to_string(), quantity: 9_000, bought_at: 0 });
+    // An hour after buying
+    price_handle.set_buy_ledger(ledger, 60 * 60);
+
+    let (row, _) = price_handle.recipe_price_overview_from_string(&"Sapphire Rings".to_string()).unwrap();
+    assert_eq!(row.number, 1_000);
+    assert_eq!(row.limited_by, LimitingFactor::BuyLimit("Gold bar".to_string()));
+    assert_eq!(row.format_limit_reset(), "Gold bar in 3h");
+}
+
+#[test]
+fn limiting_factor_without_number_per_hour() {
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+    let mut picking = Recipe { name: "Picking sapphires".to_string(), ticks: RecipeTime::Time(2.0), ..Recipe::default() };
+    picking.outputs.insert("Sapphire".to_string(), 1.0);
+    price_handle.recipe_list.add_recipe(picking);
+    price_handle.recipe_list.recipes.get_mut("Sapphire Rings").unwrap().number_per_hour = None;
+
+    let limited_by = |name: &str| price_handle.recipe_price_overview_from_string(&name.to_string()).unwrap().0.limited_by;
+    assert_eq!(limited_by("Sapphire Rings"), LimitingFactor::Coins);
+    // Neither a cost nor a rate bounds it
+    assert_eq!(limited_by("Picking sapphires"), LimitingFactor::Default);
+}
+
+#[test]
+fn backtest_ignores_later_purchases_and_overrides() {
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+    let overrides = HashMap::from([("Sapphire ring".to_string(), PriceOverride { high: 500, low: 500 })]);
+    price_handle.all_items.set_price_overrides(overrides);
+    let mut ledger = PurchaseLedger::default();
+    ledger.record(Purchase { item: "Gold bar".to_string(), quantity: 9_000, bought_at: 2_000 });
+    price_handle.set_buy_ledger(ledger, 3_000);
+
+    let history = PriceHistory::new(vec![(1_000, cheap_sapphire_prices())]);
+    let all_series = backtest(&mut price_handle, &history, &config::Membership::default());
+
+    let sapphire = all_series.iter().find(|series| series.name == "Sapphire Rings").unwrap();
+    let overview = &sapphire.points[0].overview;
+    // (980 - 2% tax) - (160 + 100), bought before the purchase was made
+    assert_eq!(overview.profit, Gp::new(701));
+    assert!(overview.overridden.is_empty());
+    assert_ne!(overview.limited_by, LimitingFactor::BuyLimit("Gold bar".to_string()));
+
+    // Restored for the current prices
+    assert_eq!(price_handle.ledger_time, 3_000);
+    assert!(price_handle.all_items.is_overridden("Sapphire ring"));
+}
+
+#[test]
+fn traded_volume_caps_number() {
+    let volume = |high_volume, low_volume| PriceDatum {
+        high_volume: Some(high_volume),
+        low_volume: Some(low_volume),
+        ..Default::default()
+    };
+    let mut volumes = PriceDataType::default();
+    volumes.add_datum("1637".to_string(), volume(600, 400)); // Sapphire ring
+    volumes.add_datum("2357".to_string(), volume(30_000, 20_000)); // Gold bar
+
+    let mut timeseries_source = InMemorySource::default();
+    timeseries_source.add_volumes(TimeSpan::OneHour, volumes);
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(timeseries_source),
+    );
+    assert_eq!(price_handle.all_items.update_item_volumes(TimeSpan::OneHour), 2);
+    price_handle.set_max_volume_fraction(Some(0.1));
+
+    // 100 Sapphire rings fill per hour, over a 6 hour session
+    let (row, _) = price_handle.recipe_price_overview_from_string(&"Sapphire Rings".to_string()).unwrap();
+    assert_eq!(row.number, 600);
+    assert_eq!(row.limited_by, LimitingFactor::Liquidity("Sapphire ring".to_string()));
+    assert_eq!(row.total_fill_time(), Some(6.0));
+}
+
+#[test]
+fn drops_simulated_around_expected_profit() {
+    let directory = fixture_path("prices");
+    let mut price_handle = price_handle(
+        Box::new(FixtureSource::new(&directory)),
+        Box::new(FixtureSource::new(&directory)),
+    );
+
+    // Ruby Rings, where half the time the ring is lost or doubled
+    let mut lucky = price_handle.recipe_list.get_recipe(&"Ruby Rings".to_string()).unwrap().clone();
+    lucky.name = "Lucky Ruby Rings".to_string();
+    lucky.outputs.clear();
+    lucky.drops.insert("Ruby ring".to_string(), RecipeDrop { chance: 0.5, quantity: DropQuantity::Fixed(2) });
+    price_handle.recipe_list.add_recipe(lucky);
+
+    let (ruby, _) = price_handle.recipe_price_overview_from_string(&"Ruby Rings".to_string()).unwrap();
+    let (lucky, _) = price_handle.recipe_price_overview_from_string(&"Lucky Ruby Rings".to_string()).unwrap();
+    assert_eq!(lucky.total_gp(), ruby.total_gp());
+
+    let names = ["Ruby Rings".to_string(), "Lucky Ruby Rings".to_string()];
+    let simulation = simulate(&price_handle, &names, &config::Membership::default(), 1_000, 3);
+    let spread: Vec<_> = simulation.distributions.iter()
+        .map(|d| (d.name.as_str(), d.p5 < d.expected, d.median, d.p95 > d.expected))
+        .collect();
+
+    // Deterministic crafting ranks higher at the 5th percentile
+    assert_eq!(spread[0], ("Ruby Rings", false, ruby.total_gp(), false));
+    assert_eq!(spread[1].0, "Lucky Ruby Rings");
+    assert!(spread[1].1 && spread[1].3, "{spread:?}");
+}
+
+#[test]
+fn chains_make_intermediates_worth_the_time() {
+    let directory = fixture_path("prices");
+    let mut price_handle = price_handle(
+        Box::new(FixtureSource::new(&directory)),
+        Box::new(FixtureSource::new(&directory)),
+    );
+
+    let mut gold_bars = Recipe { name: "Buying gold bars".to_string(), ticks: RecipeTime::Time(2.0), ..Recipe::default() };
+    gold_bars.inputs.inputs.insert("Coins".to_string(), 100.0);
+    gold_bars.outputs.insert("Gold bar".to_string(), 1.0);
+    price_handle.recipe_list.add_recipe(gold_bars);
+
+    let names = ["Ruby Rings".to_string()];
+    let chain = |price_handle: &PriceHandle| evaluate_chains(price_handle, &names, &config::Membership::default()).remove(0);
+
+    // Cheaper than a 160 GP bar, but 1.2s of Ruby Rings earns more than the 60 saved
+    let bought = chain(&price_handle);
+    let step = &bought.steps[0];
+    assert_eq!((step.item.as_str(), step.made), ("Gold bar", false));
+    assert_eq!(step.make.as_ref().unwrap().unit_cost, Gp::new(100));
+    assert_eq!(bought.chained.profit(), Gp::new(164));
+
+    price_handle.recipe_list.recipes.get_mut("Buying gold bars").unwrap().inputs.inputs.insert("Coins".to_string(), 40.0);
+    let made = chain(&price_handle);
+    assert_eq!(made.made_items().collect::<Vec<_>>(), vec!["Gold bar"]);
+    // 164 + 120 over 1.8s + 1.2s
+    assert_eq!((made.chained.profit(), format!("{:.1}", made.chained.time_sec)), (Gp::new(284), "3.0".to_string()));
+    assert_eq!(made.buying.unwrap().profit(), Gp::new(164));
+
+    // Items paid for once have no per-item cost, so neither the producer nor the chain is evaluated
+    let pay_once = Some(HashMap::from([("Ring mould".to_string(), 1.0)]));
+    price_handle.recipe_list.recipes.get_mut("Buying gold bars").unwrap().inputs.pay_once = pay_once.clone();
+    let bought = chain(&price_handle);
+    assert!(bought.steps[0].make.is_none() && !bought.steps[0].made);
+
+    price_handle.recipe_list.recipes.get_mut("Ruby Rings").unwrap().inputs.pay_once = pay_once;
+    assert!(evaluate_chains(&price_handle, &names, &config::Membership::default()).is_empty());
+}
+
+#[test]
+fn flips_ranked_by_profit_per_limit() {
+    let volume = |high_volume, low_volume| PriceDatum {
+        high_volume: Some(high_volume),
+        low_volume: Some(low_volume),
+        ..Default::default()
+    };
+    let mut volumes = PriceDataType::default();
+    volumes.add_datum("1603".to_string(), volume(200, 200)); // Ruby
+    volumes.add_datum("2357".to_string(), volume(30_000, 20_000)); // Gold bar
+    volumes.add_datum("1607".to_string(), volume(10_000, 100)); // Sapphire, rarely sold
+
+    let mut timeseries_source = InMemorySource::default();
+    timeseries_source.add_volumes(TimeSpan::OneHour, volumes);
+    let mut price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(timeseries_source),
+    );
+
+    let mut options = FlipOptions { min_hourly_volume: None, max_price_age_mins: 60, volume: TimeSpan::OneHour };
+    let membership = config::Membership::default();
+    let ranked = |flips: Vec<flips::Flip>| flips.iter().map(|flip| (flip.name.clone(), flip.profit())).collect::<Vec<_>>();
+
+    // Sapphire rings have no margin after tax
+    assert_eq!(ranked(flips::scan(&price_handle, &options, &membership)), vec![
+        ("Ruby".to_string(), Gp::new(403_000)),      // (950 - 19 tax - 900) * 13,000
+        ("Ruby ring".to_string(), Gp::new(230_000)),
+        ("Sapphire".to_string(), Gp::new(104_000)),
+        ("Gold bar".to_string(), Gp::new(70_000)),
+        ("Nature rune".to_string(), Gp::new(54_000)),
+    ]);
+
+    price_handle.all_items.update_item_volumes(TimeSpan::OneHour);
+    options.min_hourly_volume = Some(500.0);
+    assert_eq!(ranked(flips::scan(&price_handle, &options, &membership)), vec![("Gold bar".to_string(), Gp::new(70_000))]);
+    // Members can flip F2P items too
+    assert_eq!(ranked(flips::scan(&price_handle, &options, &config::Membership::P2P)), vec![("Gold bar".to_string(), Gp::new(70_000))]);
+}
+
+#[test]
+fn high_alchemy_recipes_capped_by_buy_limit() {
+    let mut prices = cheap_sapphire_prices();
+    prices.add_datum("1637".to_string(), PriceDatum { // Sapphire ring, below its alch value
+        high: Some(400),
+        high_time: Some(1_776_380_000),
+        low: Some(390),
+        low_time: Some(1_776_380_000),
+        ..Default::default()
+    });
+    let mut price_handle = price_handle(Box::new(InMemorySource::new(prices)), Box::new(InMemorySource::default()));
+    price_handle.coins = Gp::new(100_000_000);
+
+    // Only Sapphire rings alch for more than they and a Nature rune cost
+    let added = price_handle.recipe_list.add_high_alchemy_recipes(&price_handle.all_items, PricingStrategy::Instant);
+    assert_eq!(added, 1);
+
+    let mut ledger = PurchaseLedger::default();
+    ledger.record(Purchase { item: "Sapphire ring".to_string(), quantity: 5_000, bought_at: 0 });
+    price_handle.set_buy_ledger(ledger, 0);
+
+    // 540 - (400 + 100)
+    let (row, _) = price_handle.recipe_price_overview_from_string(&"High alching Sapphire ring".to_string()).unwrap();
+    assert_eq!((row.profit, row.time_sec, row.number), (Gp::new(40), Some(3.0), 5_000));
+    assert_eq!(row.limited_by, LimitingFactor::BuyLimit("Sapphire ring".to_string()));
+}
+
+#[test]
+fn high_alchemy_skips_items_without_limit() {
+    let mut prices = cheap_sapphire_prices();
+    prices.add_datum("1637".to_string(), PriceDatum { // Sapphire ring, below its alch value
+        high: Some(400),
+        high_time: Some(1_776_380_000),
+        low: Some(390),
+        low_time: Some(1_776_380_000),
+        ..Default::default()
+    });
+    let mut price_handle = price_handle(Box::new(InMemorySource::new(prices)), Box::new(InMemorySource::default()));
+    price_handle.all_items.items.get_mut("Sapphire ring").unwrap().limit = None;
+
+    let added = price_handle.recipe_list.add_high_alchemy_recipes(&price_handle.all_items, PricingStrategy::Instant);
+    assert_eq!(added, 0);
+}
+
+#[test]
+fn allocation_shares_buy_limits() {
+    let price_handle = price_handle(
+        Box::new(InMemorySource::new(cheap_sapphire_prices())),
+        Box::new(InMemorySource::default()),
+    );
+
+    let limits = AllocationLimits { bankroll: Gp::new(10_000_000), hours: 6.0, ge_slots: 8 };
+    let plan = allocation::plan(&price_handle, limits, &config::Membership::default());
+
+    // Sapphire rings use up the Gold bar buy limit, leaving none for Ruby rings
+    let chosen: Vec<_> = plan.allocations.iter().map(|a| (a.name.as_str(), a.number)).collect();
+    assert_eq!(chosen, vec![("Sapphire Rings", 10_000)]);
+    assert_eq!(plan.total_profit(), Gp::new(7_010_000));
+    assert_eq!(plan.ge_slots_used, 3);
+}